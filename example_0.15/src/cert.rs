@@ -0,0 +1,175 @@
+//! Public keys on their own, for encrypting to and verifying signatures of other people
+//!
+//! A `PublicCert` holds only the public half of a key, so services that only encrypt or
+//! verify never need to load secret key material.
+use crate::keypair::KeyPair;
+use pgp::{ArmorOptions, Deserializable, SignedPublicKey};
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use thiserror::Error as ThisError;
+
+#[derive(Debug, ThisError)]
+pub enum CertError {
+    #[error("Failed to read public key: {0}")]
+    ParseError(String),
+    #[error("Failed to load public key: {file} | Error: {source}")]
+    LoadError {
+        file: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid self-signature on public key: {0}")]
+    InvalidSelfSignature(String),
+    #[error("Failed to convert key to armored string: {0}")]
+    ToArmoredStringError(String),
+}
+
+/// Gives access to a signed public key
+///
+/// Implemented for `SignedPublicKey`, `PublicCert` and `KeyPair`, so that functions which only
+/// need the public half of a key accept any of them.
+pub trait PublicKeyProvider {
+    /// Returns the signed public key
+    fn signed_public_key(&self) -> &SignedPublicKey;
+}
+
+impl PublicKeyProvider for SignedPublicKey {
+    fn signed_public_key(&self) -> &SignedPublicKey {
+        self
+    }
+}
+
+impl PublicKeyProvider for KeyPair {
+    fn signed_public_key(&self) -> &SignedPublicKey {
+        self.public_key()
+    }
+}
+
+impl PublicKeyProvider for PublicCert {
+    fn signed_public_key(&self) -> &SignedPublicKey {
+        &self.public_key
+    }
+}
+
+/// A public key with its user IDs, subkeys and signatures, without any secret key material
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicCert {
+    public_key: SignedPublicKey,
+}
+
+impl PublicCert {
+    /// Wraps a public key after checking its self-signatures
+    pub fn new(public_key: SignedPublicKey) -> Result<Self, CertError> {
+        public_key
+            .verify()
+            .map_err(|e| CertError::InvalidSelfSignature(e.to_string()))?;
+        Ok(PublicCert { public_key })
+    }
+
+    /// Creates a PublicCert from an armored public key block
+    pub fn from_armored_string(public_key: &str) -> Result<Self, CertError> {
+        let (public_key, _) = SignedPublicKey::from_string(public_key)
+            .map_err(|e| CertError::ParseError(e.to_string()))?;
+        Self::new(public_key)
+    }
+
+    /// Creates a PublicCert from a binary public key, as written by `gpg --export`
+    pub fn from_bytes(public_key: &[u8]) -> Result<Self, CertError> {
+        let public_key = SignedPublicKey::from_bytes(Cursor::new(public_key))
+            .map_err(|e| CertError::ParseError(e.to_string()))?;
+        Self::new(public_key)
+    }
+
+    /// Loads a PublicCert from a file holding either an armored or a binary public key
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, CertError> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path).map_err(|e| CertError::LoadError {
+            file: path.to_path_buf(),
+            source: e,
+        })?;
+        let (public_key, _) = SignedPublicKey::from_reader_single(BufReader::new(file))
+            .map_err(|e| CertError::ParseError(e.to_string()))?;
+        Self::new(public_key)
+    }
+
+    /// Accessor for the public key
+    pub fn public_key(&self) -> &SignedPublicKey {
+        &self.public_key
+    }
+
+    /// Returns the armored string representation of the public key
+    pub fn to_armored_string(&self) -> Result<String, CertError> {
+        let ao = ArmorOptions {
+            headers: None,
+            ..Default::default()
+        };
+        self.public_key
+            .to_armored_string(ao)
+            .map_err(|e| CertError::ToArmoredStringError(e.to_string()))
+    }
+}
+
+impl From<&KeyPair> for PublicCert {
+    fn from(key_pair: &KeyPair) -> Self {
+        PublicCert {
+            public_key: key_pair.public_key().clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decrypt::decrypt_str;
+    use crate::encrypt::encrypt;
+    use crate::keypair::KeyAlgorithm;
+    use crate::signing::{sign_data_from_reader, verify_signed_data};
+    use crate::test_util::TestDir;
+    use pgp::ser::Serialize;
+
+    #[test]
+    fn test_public_cert() {
+        let key_pair = KeyPair::builder("test@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        let armored = key_pair.public_key_armored_string().unwrap();
+        let binary = key_pair.public_key().to_bytes().unwrap();
+
+        let cert = PublicCert::from_armored_string(&armored).unwrap();
+        assert_eq!(cert.public_key(), key_pair.public_key());
+        assert_eq!(cert, PublicCert::from_bytes(&binary).unwrap());
+        assert_eq!(cert, PublicCert::from(&key_pair));
+        assert_eq!(cert.to_armored_string().unwrap(), armored);
+
+        let directory = TestDir::new("test_public_cert");
+        let armored_path = directory.join("cert.asc");
+        let binary_path = directory.join("cert.gpg");
+        std::fs::write(&armored_path, &armored).unwrap();
+        std::fs::write(&binary_path, &binary).unwrap();
+        assert_eq!(cert, PublicCert::from_file(&armored_path).unwrap());
+        assert_eq!(cert, PublicCert::from_file(&binary_path).unwrap());
+
+        // Verifying and encrypting only need the public key
+        let signature = sign_data_from_reader(&key_pair, &mut &b"Hello, cert!"[..]).unwrap();
+        assert!(verify_signed_data(&cert, b"Hello, cert!", &signature)
+            .unwrap()
+            .is_valid());
+        let encrypted = encrypt("Hello, cert!", &cert).unwrap();
+        assert_eq!(
+            decrypt_str(&encrypted, &key_pair.secret_key_armored_string().unwrap()).unwrap(),
+            "Hello, cert!"
+        );
+
+        // A secret key is not accepted where only the public key is expected
+        let secret = key_pair.secret_key_armored_string().unwrap();
+        assert!(matches!(
+            PublicCert::from_armored_string(&secret),
+            Err(CertError::ParseError(_))
+        ));
+        assert!(matches!(
+            PublicCert::from_file(directory.join("missing.asc")),
+            Err(CertError::LoadError { .. })
+        ));
+    }
+}
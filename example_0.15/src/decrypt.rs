@@ -1,5 +1,18 @@
-//! Decrypts a message using a secret key
-use pgp::{composed::message::Message, Deserializable, SignedSecretKey};
+//! Decrypts a message using a secret key or a password, and describes messages without
+//! decrypting them
+use crate::keypair::{check_passphrase, PassphraseProvider};
+use crate::signing::{signed_content, verify_signature, SigningError, VerificationReport};
+use pgp::{
+    composed::message::{
+        decrypt_session_key, decrypt_session_key_with_password, Edata, Esk, Message,
+        PlainSessionKey,
+    },
+    crypto::{aead::AeadAlgorithm, sym::SymmetricKeyAlgorithm},
+    packet::{self, PublicKeyEncryptedSessionKey},
+    ser::Serialize,
+    types::{CompressionAlgorithm, EskType, Fingerprint, KeyId, PkeskVersion, PublicKeyTrait},
+    Deserializable, SignedPublicKey, SignedSecretKey,
+};
 use std::io::Cursor;
 use thiserror::Error as ThisError;
 
@@ -13,15 +26,83 @@ pub enum DecryptError {
     PgpError(#[from] pgp::errors::Error),
     #[error("Failed to read decrypted data: {0}")]
     ReadDecryptedDataError(String),
+    #[error("Wrong passphrase for secret key")]
+    WrongPassphrase,
+    #[error("Message is not encrypted with a password")]
+    NotPasswordEncrypted,
+    #[error("Wrong password for message")]
+    WrongPassword,
+    #[error("Message is not encrypted")]
+    NotEncrypted,
+    #[error("None of the secret keys can open the message, it is encrypted to {0:?}")]
+    NoMatchingKey(Vec<Recipient>),
+    #[error("Failed to verify message signature: {0}")]
+    SigningError(#[from] SigningError),
+}
+
+/// Extracts the plaintext bytes of a decrypted message
+fn message_bytes(decrypted: Message) -> Result<Vec<u8>, DecryptError> {
+    decrypted
+        .get_content()?
+        .ok_or_else(|| DecryptError::NoContent)
+}
+
+/// Extracts the plaintext of a decrypted message
+fn message_text(decrypted: Message) -> Result<String, DecryptError> {
+    Ok(String::from_utf8(message_bytes(decrypted)?)?)
+}
+
+/// Parses a message that is either ASCII armored or binary
+pub(crate) fn parse_message(data: &[u8]) -> Result<Message, pgp::errors::Error> {
+    let start = data
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(data.len());
+    if data[start..].starts_with(b"-----BEGIN PGP") {
+        Ok(Message::from_armor_single(Cursor::new(data))?.0)
+    } else {
+        Message::from_bytes(data)
+    }
 }
 
 /// Decrypts a message using a secret key
 pub fn decrypt(msg: Message, secret_key: &SignedSecretKey) -> Result<String, DecryptError> {
-    let decrypted = msg.decrypt(|| String::new(), &[secret_key])?.0;
-    let bytes = decrypted
-        .get_content()?
-        .ok_or_else(|| DecryptError::NoContent)?;
-    Ok(String::from_utf8(bytes)?)
+    decrypt_with_passphrase(msg, secret_key, "")
+}
+
+/// Decrypts a message using a passphrase protected secret key
+pub fn decrypt_with_passphrase<P>(
+    msg: Message,
+    secret_key: &SignedSecretKey,
+    passphrase: &P,
+) -> Result<String, DecryptError>
+where
+    P: PassphraseProvider + ?Sized,
+{
+    message_text(decrypt_message(msg, secret_key, passphrase)?)
+}
+
+fn decrypt_message<P>(
+    msg: Message,
+    secret_key: &SignedSecretKey,
+    passphrase: &P,
+) -> Result<Message, DecryptError>
+where
+    P: PassphraseProvider + ?Sized,
+{
+    // Ask the provider once, it may prompt the user
+    let passphrase = passphrase.passphrase();
+    let decrypted = msg
+        .decrypt(|| passphrase.clone(), &[secret_key])
+        .map_err(|e| {
+            if check_passphrase(secret_key, passphrase.as_str()) {
+                DecryptError::PgpError(e)
+            } else {
+                DecryptError::WrongPassphrase
+            }
+        })?
+        .0;
+    Ok(decrypted)
 }
 
 /// Decrypts a message using a secret key passed as a string
@@ -31,6 +112,447 @@ pub fn decrypt_str(armored_msg: &str, seckey_str: &str) -> Result<String, Decryp
     decrypt(msg, &privkey)
 }
 
+/// Decrypts a message that was encrypted with a password
+pub fn decrypt_with_password<P>(msg: Message, password: &P) -> Result<String, DecryptError>
+where
+    P: PassphraseProvider + ?Sized,
+{
+    message_text(decrypt_message_with_password(msg, password)?)
+}
+
+fn decrypt_message_with_password<P>(msg: Message, password: &P) -> Result<Message, DecryptError>
+where
+    P: PassphraseProvider + ?Sized,
+{
+    let Message::Encrypted { esk, edata, .. } = &msg else {
+        return Err(DecryptError::NotPasswordEncrypted);
+    };
+    let mut skesks = esk
+        .iter()
+        .filter_map(|esk| match esk {
+            Esk::SymKeyEncryptedSessionKey(skesk) => Some(skesk),
+            _ => None,
+        })
+        .peekable();
+    if skesks.peek().is_none() {
+        return Err(DecryptError::NotPasswordEncrypted);
+    }
+
+    // Each password packet is tried in turn, as the message may have been encrypted with
+    // several passwords. A wrong password fails the authentication of an AEAD protected session
+    // key, or yields a garbage session key that does not fit its algorithm or fails the
+    // integrity check.
+    let password = password.passphrase();
+    let mut error = None;
+    for skesk in skesks {
+        let Ok(session_key) = decrypt_session_key_with_password(skesk, || password.clone()) else {
+            continue;
+        };
+        if let PlainSessionKey::V3_4 { sym_alg, key } = &session_key {
+            if key.len() != sym_alg.key_size() {
+                continue;
+            }
+        }
+        match edata.decrypt(session_key) {
+            Ok(decrypted) => return Ok(decrypted),
+            Err(pgp::errors::Error::MdcError) => {}
+            Err(e) => error = Some(e),
+        }
+    }
+    Err(error.map_or(DecryptError::WrongPassword, DecryptError::PgpError))
+}
+
+/// Decrypts an armored message that was encrypted with a password
+pub fn decrypt_with_password_str(
+    armored_msg: &str,
+    password: &str,
+) -> Result<String, DecryptError> {
+    let msg = Message::from_armor_single(Cursor::new(armored_msg))?.0;
+    decrypt_with_password(msg, password)
+}
+
+/// Decrypts a binary or armored message holding arbitrary bytes using a secret key
+pub fn decrypt_bytes(
+    encrypted: &[u8],
+    secret_key: &SignedSecretKey,
+) -> Result<Vec<u8>, DecryptError> {
+    decrypt_bytes_with_passphrase(encrypted, secret_key, "")
+}
+
+/// Decrypts a binary or armored message holding arbitrary bytes using a passphrase protected
+/// secret key
+pub fn decrypt_bytes_with_passphrase<P>(
+    encrypted: &[u8],
+    secret_key: &SignedSecretKey,
+    passphrase: &P,
+) -> Result<Vec<u8>, DecryptError>
+where
+    P: PassphraseProvider + ?Sized,
+{
+    let msg = parse_message(encrypted)?;
+    message_bytes(decrypt_message(msg, secret_key, passphrase)?)
+}
+
+/// Decrypts a binary or armored message holding arbitrary bytes using a password
+pub fn decrypt_bytes_with_password<P>(
+    encrypted: &[u8],
+    password: &P,
+) -> Result<Vec<u8>, DecryptError>
+where
+    P: PassphraseProvider + ?Sized,
+{
+    let msg = parse_message(encrypted)?;
+    message_bytes(decrypt_message_with_password(msg, password)?)
+}
+
+/// A recipient of a message, as named in its public key encrypted session key packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recipient {
+    /// The key ID of the key or subkey, from a v3 packet
+    KeyId(KeyId),
+    /// The fingerprint of the key or subkey, from a v6 packet
+    Fingerprint(Fingerprint),
+    /// The packet does not say which key it is for, as with `gpg --throw-keyids`
+    Anonymous,
+}
+
+impl From<&PublicKeyEncryptedSessionKey> for Recipient {
+    fn from(pkesk: &PublicKeyEncryptedSessionKey) -> Self {
+        match pkesk {
+            PublicKeyEncryptedSessionKey::V3 { id, .. } if !id.is_wildcard() => {
+                Recipient::KeyId(id.clone())
+            }
+            PublicKeyEncryptedSessionKey::V6 {
+                fingerprint: Some(fingerprint),
+                ..
+            } => Recipient::Fingerprint(fingerprint.clone()),
+            _ => Recipient::Anonymous,
+        }
+    }
+}
+
+/// The plaintext of a message and the key that opened it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptedMessage {
+    pub data: Vec<u8>,
+    /// The fingerprint of the primary key of the secret key that opened the message
+    pub fingerprint: Fingerprint,
+    /// The key ID of the key or subkey the session key was encrypted to
+    pub key_id: KeyId,
+}
+
+/// The key or subkey of a secret key that a session key may be encrypted to
+enum DecryptionKey<'a> {
+    Primary(&'a packet::SecretKey),
+    Subkey(&'a packet::SecretSubkey),
+}
+
+impl DecryptionKey<'_> {
+    fn key_id(&self) -> KeyId {
+        match self {
+            DecryptionKey::Primary(key) => key.key_id(),
+            DecryptionKey::Subkey(key) => key.key_id(),
+        }
+    }
+
+    fn decrypt_session_key(
+        &self,
+        pkesk: &PublicKeyEncryptedSessionKey,
+        passphrase: &str,
+    ) -> Result<PlainSessionKey, pgp::errors::Error> {
+        let typ = match pkesk.version() {
+            PkeskVersion::V6 => EskType::V6,
+            _ => EskType::V3_4,
+        };
+        let passphrase = || passphrase.to_string();
+        match self {
+            DecryptionKey::Primary(key) => {
+                decrypt_session_key(*key, passphrase, pkesk.values()?, typ)
+            }
+            DecryptionKey::Subkey(key) => {
+                decrypt_session_key(*key, passphrase, pkesk.values()?, typ)
+            }
+        }
+    }
+}
+
+/// Lists the keys of a secret key that could have a session key encrypted to them. For a
+/// named recipient only the key or subkey with that ID, for an anonymous one every key or
+/// subkey that uses the same algorithm.
+fn decryption_keys<'a>(
+    secret_key: &'a SignedSecretKey,
+    pkesk: &PublicKeyEncryptedSessionKey,
+) -> Vec<DecryptionKey<'a>> {
+    fn fits(pkesk: &PublicKeyEncryptedSessionKey, key: &impl PublicKeyTrait) -> bool {
+        if Recipient::from(pkesk) == Recipient::Anonymous {
+            key.is_encryption_key() && pkesk.algorithm().is_ok_and(|alg| alg == key.algorithm())
+        } else {
+            pkesk.match_identity(key)
+        }
+    }
+    let mut keys = Vec::new();
+    if fits(pkesk, &secret_key.primary_key) {
+        keys.push(DecryptionKey::Primary(&secret_key.primary_key));
+    }
+    for subkey in &secret_key.secret_subkeys {
+        if fits(pkesk, &subkey.key) {
+            keys.push(DecryptionKey::Subkey(&subkey.key));
+        }
+    }
+    keys
+}
+
+/// Decrypts a binary or armored message with whichever of the secret keys it was encrypted to
+///
+/// See `decrypt_with_keys_and_passphrase`.
+pub fn decrypt_with_keys(
+    encrypted: &[u8],
+    secret_keys: &[&SignedSecretKey],
+) -> Result<DecryptedMessage, DecryptError> {
+    decrypt_with_keys_and_passphrase(encrypted, secret_keys, "")
+}
+
+/// Decrypts a binary or armored message with whichever of the passphrase protected secret
+/// keys it was encrypted to
+///
+/// The keys are picked by the key IDs the message names as its recipients. Recipients that
+/// are not named are tried against every key with a matching algorithm, after the named ones.
+///
+/// # Returns
+/// The plaintext together with the key that opened it. Fails with `WrongPassphrase` if a key
+/// the message names does not unlock, and with `NoMatchingKey` if the message is not encrypted
+/// to any of the keys.
+pub fn decrypt_with_keys_and_passphrase<P>(
+    encrypted: &[u8],
+    secret_keys: &[&SignedSecretKey],
+    passphrase: &P,
+) -> Result<DecryptedMessage, DecryptError>
+where
+    P: PassphraseProvider + ?Sized,
+{
+    let msg = parse_message(encrypted)?;
+    let Message::Encrypted { esk, edata } = &msg else {
+        return Err(DecryptError::NotEncrypted);
+    };
+    let mut pkesks: Vec<&PublicKeyEncryptedSessionKey> = esk
+        .iter()
+        .filter_map(|esk| match esk {
+            Esk::PublicKeyEncryptedSessionKey(pkesk) => Some(pkesk),
+            _ => None,
+        })
+        .collect();
+    // Named recipients first, they need a single attempt
+    pkesks.sort_by_key(|pkesk| Recipient::from(*pkesk) == Recipient::Anonymous);
+
+    // Ask the provider once, it may prompt the user
+    let passphrase = passphrase.passphrase();
+    // Keys that failed on a recipient naming them, each kept once
+    let mut failed: Vec<&SignedSecretKey> = Vec::new();
+    let mut error = None;
+    for pkesk in &pkesks {
+        for secret_key in secret_keys {
+            for key in decryption_keys(secret_key, pkesk) {
+                // Trying a key on an anonymous recipient that is not for it yields a garbage
+                // session key, which fails the integrity check
+                let decrypted = key
+                    .decrypt_session_key(pkesk, &passphrase)
+                    .and_then(|session_key| edata.decrypt(session_key));
+                match decrypted {
+                    Ok(decrypted) => {
+                        return Ok(DecryptedMessage {
+                            data: message_bytes(decrypted)?,
+                            fingerprint: secret_key.fingerprint(),
+                            key_id: key.key_id(),
+                        })
+                    }
+                    Err(e) if Recipient::from(*pkesk) != Recipient::Anonymous => {
+                        if !failed
+                            .iter()
+                            .any(|failed| failed.fingerprint() == secret_key.fingerprint())
+                        {
+                            failed.push(secret_key);
+                        }
+                        error = Some(e);
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+
+    if failed
+        .iter()
+        .any(|secret_key| !check_passphrase(secret_key, passphrase.as_str()))
+    {
+        return Err(DecryptError::WrongPassphrase);
+    }
+    match error {
+        Some(e) => Err(DecryptError::PgpError(e)),
+        None => Err(DecryptError::NoMatchingKey(
+            pkesks.into_iter().map(Recipient::from).collect(),
+        )),
+    }
+}
+
+/// The packet format the encrypted data of a message is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionContainer {
+    /// Encrypted data without integrity protection, from before RFC 4880. Current
+    /// implementations refuse to decrypt it.
+    Unprotected,
+    /// SEIPDv1 with a modification detection code
+    SeipdV1,
+    /// SEIPDv2 (RFC 9580) with authenticated encryption
+    SeipdV2 {
+        cipher: SymmetricKeyAlgorithm,
+        aead: AeadAlgorithm,
+        chunk_size: u8,
+    },
+}
+
+/// What can be learned about a message without decrypting it
+///
+/// Compression and signatures inside the encryption only show up after decrypting, so for an
+/// encrypted message `compression` and `signed` describe the layers outside of it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageInfo {
+    /// The recipients of the public key encrypted session keys, in message order
+    pub recipients: Vec<Recipient>,
+    /// True if the message can also be opened with a password
+    pub password_encrypted: bool,
+    /// The encrypted data format, None if the message is not encrypted
+    pub encryption: Option<EncryptionContainer>,
+    /// The compression algorithm of the outermost compressed layer
+    pub compression: Option<CompressionAlgorithm>,
+    /// True if the message carries at least one signature
+    pub signed: bool,
+}
+
+/// Keeps the first byte written to it and discards the rest
+#[derive(Default)]
+struct FirstByte(Option<u8>);
+
+impl std::io::Write for FirstByte {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.0.is_none() {
+            self.0 = buf.first().copied();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Describes a binary or armored message without needing any key or password
+///
+/// Lists who the message is encrypted to, so operators can tell who is able to read it.
+/// Compressed layers outside of the encryption are decompressed in memory to look inside them,
+/// so this costs as much memory as the uncompressed message for such messages.
+pub fn message_info(message: &[u8]) -> Result<MessageInfo, DecryptError> {
+    let mut info = MessageInfo::default();
+    let mut msg = parse_message(message)?;
+    loop {
+        msg = match msg {
+            Message::Encrypted { esk, edata } => {
+                for esk in &esk {
+                    match esk {
+                        Esk::PublicKeyEncryptedSessionKey(pkesk) => {
+                            info.recipients.push(Recipient::from(pkesk))
+                        }
+                        Esk::SymKeyEncryptedSessionKey(_) => info.password_encrypted = true,
+                    }
+                }
+                info.encryption = Some(match &edata {
+                    Edata::SymEncryptedData(_) => EncryptionContainer::Unprotected,
+                    Edata::SymEncryptedProtectedData(seipd) => match seipd.data() {
+                        packet::Data::V1 { .. } => EncryptionContainer::SeipdV1,
+                        packet::Data::V2 {
+                            sym_alg,
+                            aead,
+                            chunk_size,
+                            ..
+                        } => EncryptionContainer::SeipdV2 {
+                            cipher: *sym_alg,
+                            aead: *aead,
+                            chunk_size: *chunk_size,
+                        },
+                    },
+                });
+                return Ok(info);
+            }
+            Message::Signed { message, .. } => {
+                info.signed = true;
+                match message {
+                    Some(message) => *message,
+                    None => return Ok(info),
+                }
+            }
+            Message::Compressed(data) => {
+                // The packet has no accessor for its algorithm, which is the first byte of the
+                // packet body, so keep that byte while writing the body out
+                let mut first_byte = FirstByte::default();
+                data.to_writer(&mut first_byte)?;
+                if let Some(algorithm) = first_byte.0 {
+                    info.compression
+                        .get_or_insert(CompressionAlgorithm::from(algorithm));
+                }
+                Message::Compressed(data).decompress()?
+            }
+            Message::Literal(_) => return Ok(info),
+        };
+    }
+}
+
+/// The plaintext of a decrypted message and the result of checking each of its signatures
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedMessage {
+    pub data: Vec<u8>,
+    /// One entry per signature in the message, outermost first
+    pub signatures: Vec<VerificationReport>,
+}
+
+impl VerifiedMessage {
+    /// Returns true if the message is signed and every signature is valid
+    pub fn is_verified(&self) -> bool {
+        !self.signatures.is_empty() && self.signatures.iter().all(VerificationReport::is_valid)
+    }
+}
+
+/// Decrypts a signed message and checks its signatures against the senders' public keys
+///
+/// A signature by a key that is not among `sender_certs` is reported as failed with
+/// `VerificationFailure::UnknownKey`. Decryption still succeeds if a signature does not verify,
+/// so check `VerifiedMessage::is_verified` before trusting the data.
+pub fn decrypt_and_verify(
+    secret_key: &SignedSecretKey,
+    sender_certs: &[&SignedPublicKey],
+    armored_msg: &str,
+) -> Result<VerifiedMessage, DecryptError> {
+    decrypt_and_verify_with_passphrase(secret_key, sender_certs, armored_msg, "")
+}
+
+/// Decrypts a signed message using a passphrase protected secret key and checks its signatures
+pub fn decrypt_and_verify_with_passphrase<P>(
+    secret_key: &SignedSecretKey,
+    sender_certs: &[&SignedPublicKey],
+    armored_msg: &str,
+    passphrase: &P,
+) -> Result<VerifiedMessage, DecryptError>
+where
+    P: PassphraseProvider + ?Sized,
+{
+    let msg = parse_message(armored_msg.as_bytes())?;
+    let (data, signatures) = signed_content(decrypt_message(msg, secret_key, passphrase)?)?
+        .ok_or(DecryptError::NoContent)?;
+    let signatures = signatures
+        .iter()
+        .map(|signature| verify_signature(signature, &data[..], sender_certs))
+        .collect::<Result<_, _>>()?;
+    Ok(VerifiedMessage { data, signatures })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,4 +609,463 @@ nX4jlknFe/XztC6foMTCoCzcTPpYTgygBInP2hAor0qbeqjs8UbhjrJmkaId4jqB
         let decrypted_msg = decrypt_str(encrypted_msg, sec_key_armored).unwrap();
         assert_eq!(decrypted_msg, plain_msg);
     }
+
+    #[test]
+    fn test_decrypt_with_passphrase() {
+        use crate::encrypt::encrypt;
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+
+        let key_pair = KeyPair::builder("foo bar baz")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .passphrase("correct horse")
+            .build()
+            .unwrap();
+        let plain_msg = "Testing testing this is a secret";
+
+        let armored = encrypt(plain_msg, key_pair.public_key()).unwrap();
+        let msg = Message::from_armor_single(Cursor::new(armored)).unwrap().0;
+        let decrypted =
+            decrypt_with_passphrase(msg.clone(), key_pair.secret_key(), "correct horse").unwrap();
+        assert_eq!(decrypted, plain_msg);
+
+        let prompt = || "correct horse".to_string();
+        let decrypted =
+            decrypt_with_passphrase(msg.clone(), key_pair.secret_key(), &prompt).unwrap();
+        assert_eq!(decrypted, plain_msg);
+
+        assert!(matches!(
+            decrypt_with_passphrase(msg.clone(), key_pair.secret_key(), "battery staple"),
+            Err(DecryptError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            decrypt(msg, key_pair.secret_key()),
+            Err(DecryptError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_with_password() {
+        use crate::encrypt::{
+            encrypt_with_options, encrypt_with_password, encrypt_with_password_and_recipients,
+            EncryptOptions, SeipdVersion,
+        };
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+        use pgp::crypto::hash::HashAlgorithm;
+        use pgp::packet::{SymEncryptedProtectedData, SymKeyEncryptedSessionKey};
+        use pgp::types::StringToKey;
+        use rand::prelude::*;
+
+        let plain_msg = "Testing testing this is a secret";
+        let encrypted = encrypt_with_password(plain_msg, "open sesame").unwrap();
+        assert_eq!(
+            decrypt_with_password_str(&encrypted, "open sesame").unwrap(),
+            plain_msg
+        );
+        assert!(matches!(
+            decrypt_with_password_str(&encrypted, "open barley"),
+            Err(DecryptError::WrongPassword)
+        ));
+
+        // A mixed message opens with either the password or the recipient's key
+        let key_pair = KeyPair::builder("foo bar baz")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        let encrypted = encrypt_with_password_and_recipients(
+            plain_msg,
+            "open sesame",
+            &[key_pair.public_key()],
+        )
+        .unwrap();
+        let msg = Message::from_armor_single(Cursor::new(&encrypted))
+            .unwrap()
+            .0;
+        assert_eq!(
+            decrypt_with_password(msg.clone(), "open sesame").unwrap(),
+            plain_msg
+        );
+        assert_eq!(decrypt(msg, key_pair.secret_key()).unwrap(), plain_msg);
+
+        // A message only encrypted to keys has no password to try
+        let encrypted = crate::encrypt::encrypt(plain_msg, key_pair.public_key()).unwrap();
+        assert!(matches!(
+            decrypt_with_password_str(&encrypted, "open sesame"),
+            Err(DecryptError::NotPasswordEncrypted)
+        ));
+
+        // A wrong password fails the AEAD session key check of a v6 message as well
+        let options = EncryptOptions {
+            version: SeipdVersion::V2 {
+                aead: AeadAlgorithm::Ocb,
+                chunk_size: 6,
+            },
+            ..Default::default()
+        };
+        let encrypted_v2 =
+            encrypt_with_options(plain_msg, Some("open sesame"), &[], &options).unwrap();
+        assert!(matches!(
+            decrypt_with_password_str(&encrypted_v2, "open barley"),
+            Err(DecryptError::WrongPassword)
+        ));
+
+        // Other failures are not blamed on the password
+        let Message::Encrypted { esk, .. } = Message::from_armor_single(Cursor::new(
+            &encrypt_with_password(plain_msg, "open sesame").unwrap(),
+        ))
+        .unwrap()
+        .0
+        else {
+            panic!("expected an encrypted message");
+        };
+        let Message::Encrypted { edata, .. } =
+            Message::from_armor_single(Cursor::new(&encrypted_v2))
+                .unwrap()
+                .0
+        else {
+            panic!("expected an encrypted message");
+        };
+        assert!(matches!(
+            decrypt_with_password(Message::Encrypted { esk, edata }, "open sesame"),
+            Err(DecryptError::PgpError(_))
+        ));
+
+        // Every password of a message with several is tried
+        let mut rng = StdRng::from_entropy();
+        let alg = SymmetricKeyAlgorithm::AES128;
+        let session_key = alg.new_session_key(&mut rng);
+        let esk = ["open sesame", "open barley"]
+            .into_iter()
+            .map(|password| {
+                let s2k = StringToKey::new_iterated(&mut rng, HashAlgorithm::SHA2_256, 224);
+                Esk::SymKeyEncryptedSessionKey(
+                    SymKeyEncryptedSessionKey::encrypt_v4(
+                        || password.to_string(),
+                        &session_key,
+                        s2k,
+                        alg,
+                    )
+                    .unwrap(),
+                )
+            })
+            .collect();
+        let data = Message::new_literal("", plain_msg).to_bytes().unwrap();
+        let edata = Edata::SymEncryptedProtectedData(
+            SymEncryptedProtectedData::encrypt_seipdv1(&mut rng, alg, &session_key, &data).unwrap(),
+        );
+        let msg = Message::Encrypted { esk, edata };
+        assert_eq!(
+            decrypt_with_password(msg.clone(), "open barley").unwrap(),
+            plain_msg
+        );
+        assert_eq!(
+            decrypt_with_password(msg.clone(), "open sesame").unwrap(),
+            plain_msg
+        );
+        assert!(matches!(
+            decrypt_with_password(msg, "open wheat"),
+            Err(DecryptError::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_bytes() {
+        use crate::encrypt::{encrypt_bytes, encrypt_bytes_with_options, EncryptOptions};
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+
+        let key_pair = KeyPair::builder("foo bar baz")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        // Not valid UTF-8, and includes line endings that must not be normalized
+        let data = (0..=255u8)
+            .chain([0xff, 0xfe, b'\r', b'\n', b'\n'])
+            .collect::<Vec<_>>();
+
+        let encrypted = encrypt_bytes(&data, &[key_pair.public_key()]).unwrap();
+        assert_eq!(
+            decrypt_bytes(&encrypted, key_pair.secret_key()).unwrap(),
+            data
+        );
+
+        let msg = parse_message(&encrypted).unwrap();
+        let Message::Literal(literal) = msg
+            .decrypt(String::new, &[key_pair.secret_key()])
+            .unwrap()
+            .0
+        else {
+            panic!("Expected a literal message");
+        };
+        assert!(literal.is_binary());
+
+        // Armored input is accepted as well
+        let armored = parse_message(&encrypted)
+            .unwrap()
+            .to_armored_string(pgp::ArmorOptions::default())
+            .unwrap();
+        assert_eq!(
+            decrypt_bytes(armored.as_bytes(), key_pair.secret_key()).unwrap(),
+            data
+        );
+
+        let encrypted =
+            encrypt_bytes_with_options(&data, Some("open sesame"), &[], &EncryptOptions::default())
+                .unwrap();
+        assert_eq!(
+            decrypt_bytes_with_password(&encrypted, "open sesame").unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_decrypt_and_verify() {
+        use crate::encrypt::{encrypt_bytes, sign_and_encrypt};
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+        use crate::signing::VerificationFailure;
+        use pgp::types::PublicKeyTrait;
+
+        let new_key = |user_id: &str| {
+            KeyPair::builder(user_id)
+                .algorithm(KeyAlgorithm::Curve25519)
+                .build()
+                .unwrap()
+        };
+        let sender = new_key("sender@example.com");
+        let recipient = new_key("recipient@example.com");
+        let data = b"Signed and sealed\n\x00\xff";
+
+        let armored = sign_and_encrypt(&sender, &[recipient.public_key()], data).unwrap();
+        let verified =
+            decrypt_and_verify(recipient.secret_key(), &[sender.public_key()], &armored).unwrap();
+        assert_eq!(verified.data, data);
+        assert_eq!(verified.signatures.len(), 1);
+        assert_eq!(
+            verified.signatures[0].signer_fingerprint,
+            Some(sender.public_key().fingerprint())
+        );
+        assert!(verified.is_verified());
+
+        // The plaintext is still returned when the sender's key is unknown
+        let stranger = new_key("stranger@example.com");
+        let verified =
+            decrypt_and_verify(recipient.secret_key(), &[stranger.public_key()], &armored).unwrap();
+        assert_eq!(verified.data, data);
+        assert_eq!(
+            verified.signatures[0].failure,
+            Some(VerificationFailure::UnknownKey)
+        );
+        assert_eq!(
+            verified.signatures[0].signer_key_id,
+            Some(sender.public_key().key_id())
+        );
+        assert!(!verified.is_verified());
+
+        // An unsigned message has no signatures to check
+        let unsigned = encrypt_bytes(data, &[recipient.public_key()]).unwrap();
+        let unsigned = Message::from_bytes(&unsigned[..])
+            .unwrap()
+            .to_armored_string(Default::default())
+            .unwrap();
+        let verified =
+            decrypt_and_verify(recipient.secret_key(), &[sender.public_key()], &unsigned).unwrap();
+        assert!(verified.signatures.is_empty());
+        assert!(!verified.is_verified());
+    }
+
+    #[test]
+    fn test_decrypt_with_keys() {
+        use crate::encrypt::{encrypt_bytes_with_options, EncryptOptions, SeipdVersion};
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+        use pgp::crypto::aead::AeadAlgorithm;
+        use pgp::ser::Serialize;
+
+        let new_key = |user_id: &str, passphrase: &str| {
+            KeyPair::builder(user_id)
+                .algorithm(KeyAlgorithm::Curve25519)
+                .passphrase(passphrase)
+                .build()
+                .unwrap()
+        };
+        let alice = new_key("alice@example.com", "");
+        let bob = new_key("bob@example.com", "correct horse");
+        let carol = new_key("carol@example.com", "");
+        let dave = new_key("dave@example.com", "correct horse");
+        let data = b"For Bob and Carol";
+        let carol_subkey_id = carol.public_key().public_subkeys[0].key.key_id();
+
+        for version in [
+            SeipdVersion::V1,
+            SeipdVersion::V2 {
+                aead: AeadAlgorithm::Ocb,
+                chunk_size: 6,
+            },
+        ] {
+            let options = EncryptOptions {
+                cipher: None,
+                version,
+            };
+            let encrypted = encrypt_bytes_with_options(
+                data,
+                None,
+                &[bob.public_key(), carol.public_key()],
+                &options,
+            )
+            .unwrap();
+
+            // The key is picked from the recipients, whatever order the keys are given in
+            let decrypted =
+                decrypt_with_keys(&encrypted, &[alice.secret_key(), carol.secret_key()]).unwrap();
+            assert_eq!(decrypted.data, data);
+            assert_eq!(decrypted.fingerprint, carol.public_key().fingerprint());
+            assert_eq!(decrypted.key_id, carol_subkey_id);
+            let decrypted = decrypt_with_keys_and_passphrase(
+                &encrypted,
+                &[alice.secret_key(), bob.secret_key()],
+                "correct horse",
+            )
+            .unwrap();
+            assert_eq!(decrypted.fingerprint, bob.public_key().fingerprint());
+            assert!(matches!(
+                decrypt_with_keys_and_passphrase(&encrypted, &[bob.secret_key()], "battery staple"),
+                Err(DecryptError::WrongPassphrase)
+            ));
+            match decrypt_with_keys(&encrypted, &[alice.secret_key()]) {
+                Err(DecryptError::NoMatchingKey(recipients)) => {
+                    assert_eq!(recipients.len(), 2);
+                    assert!(!recipients.contains(&Recipient::Anonymous));
+                }
+                other => panic!("Expected NoMatchingKey, got {:?}", other),
+            }
+
+            // Hide the recipients, as `gpg --throw-keyids` does, so every key has to be tried
+            let Message::Encrypted { mut esk, edata } = parse_message(&encrypted).unwrap() else {
+                panic!("Expected an encrypted message");
+            };
+            for esk in &mut esk {
+                match esk {
+                    Esk::PublicKeyEncryptedSessionKey(PublicKeyEncryptedSessionKey::V3 {
+                        id,
+                        ..
+                    }) => *id = KeyId::from_slice(&[0; 8]).unwrap(),
+                    Esk::PublicKeyEncryptedSessionKey(PublicKeyEncryptedSessionKey::V6 {
+                        fingerprint,
+                        ..
+                    }) => *fingerprint = None,
+                    _ => {}
+                }
+            }
+            let anonymous = Message::Encrypted { esk, edata }.to_bytes().unwrap();
+            let decrypted =
+                decrypt_with_keys(&anonymous, &[alice.secret_key(), carol.secret_key()]).unwrap();
+            assert_eq!(decrypted.data, data);
+            assert_eq!(decrypted.fingerprint, carol.public_key().fingerprint());
+            assert_eq!(decrypted.key_id, carol_subkey_id);
+            assert_eq!(
+                decrypt_with_keys(&anonymous, &[alice.secret_key()])
+                    .unwrap_err()
+                    .to_string(),
+                DecryptError::NoMatchingKey(vec![Recipient::Anonymous, Recipient::Anonymous])
+                    .to_string()
+            );
+            // A protected key that only failed on hidden recipients is not blamed on the
+            // passphrase
+            assert!(matches!(
+                decrypt_with_keys_and_passphrase(
+                    &anonymous,
+                    &[alice.secret_key(), dave.secret_key()],
+                    "battery staple"
+                ),
+                Err(DecryptError::NoMatchingKey(_))
+            ));
+        }
+
+        let literal = Message::new_literal_bytes("", data).to_bytes().unwrap();
+        assert!(matches!(
+            decrypt_with_keys(&literal, &[alice.secret_key()]),
+            Err(DecryptError::NotEncrypted)
+        ));
+    }
+
+    #[test]
+    fn test_message_info() {
+        use crate::encrypt::{encrypt_with_options, EncryptOptions, SeipdVersion};
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+        use crate::signing::sign_message;
+        use pgp::crypto::aead::AeadAlgorithm;
+
+        let new_key = |user_id: &str| {
+            KeyPair::builder(user_id)
+                .algorithm(KeyAlgorithm::Curve25519)
+                .build()
+                .unwrap()
+        };
+        let alice = new_key("alice@example.com");
+        let bob = new_key("bob@example.com");
+        let recipients = [alice.public_key(), bob.public_key()];
+        let subkey_ids: Vec<Recipient> = recipients
+            .iter()
+            .map(|key| Recipient::KeyId(key.public_subkeys[0].key.key_id()))
+            .collect();
+
+        let encrypted = encrypt_with_options(
+            "Who can read this?",
+            Some("open sesame"),
+            &recipients,
+            &EncryptOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            message_info(encrypted.as_bytes()).unwrap(),
+            MessageInfo {
+                recipients: subkey_ids,
+                password_encrypted: true,
+                encryption: Some(EncryptionContainer::SeipdV1),
+                compression: None,
+                signed: false,
+            }
+        );
+
+        let options = EncryptOptions {
+            cipher: Some(SymmetricKeyAlgorithm::AES256),
+            version: SeipdVersion::V2 {
+                aead: AeadAlgorithm::Ocb,
+                chunk_size: 6,
+            },
+        };
+        let encrypted =
+            encrypt_with_options("Who can read this?", None, &[bob.public_key()], &options)
+                .unwrap();
+        let info = message_info(encrypted.as_bytes()).unwrap();
+        assert_eq!(
+            info.recipients,
+            vec![Recipient::Fingerprint(
+                bob.public_key().public_subkeys[0].key.fingerprint()
+            )]
+        );
+        assert!(!info.password_encrypted);
+        assert_eq!(
+            info.encryption,
+            Some(EncryptionContainer::SeipdV2 {
+                cipher: SymmetricKeyAlgorithm::AES256,
+                aead: AeadAlgorithm::Ocb,
+                chunk_size: 6,
+            })
+        );
+
+        // Messages that are not encrypted
+        let signed = sign_message(&alice, "Signed, not sealed").unwrap();
+        let info = message_info(signed.as_bytes()).unwrap();
+        assert!(info.signed);
+        assert_eq!(info.encryption, None);
+        let compressed = Message::new_literal("none", "Squeezed")
+            .compress(CompressionAlgorithm::ZLIB)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        let info = message_info(&compressed).unwrap();
+        assert_eq!(info.compression, Some(CompressionAlgorithm::ZLIB));
+        assert!(!info.signed);
+        assert!(info.recipients.is_empty());
+
+        assert!(message_info(b"not a message").is_err());
+    }
 }
@@ -1,6 +1,20 @@
 //! Encrypts a message using a public key
+use crate::cert::PublicKeyProvider;
+use crate::keypair::{
+    is_expired_at, newest_self_signature, subkey_expires_at, KeyPair, PassphraseProvider,
+};
+use crate::revocation::{revocation_status, subkey_revocation};
+use crate::signing::{sign_literal_message, SignOptions, SigningError};
+use chrono::Utc;
 use pgp::{
-    composed::message::Message, composed::signed_key::*, crypto::sym::SymmetricKeyAlgorithm,
+    composed::message::{Edata, Esk, Message},
+    composed::signed_key::*,
+    crypto::{aead::AeadAlgorithm, hash::HashAlgorithm, sym::SymmetricKeyAlgorithm},
+    packet::{
+        self, PublicKeyEncryptedSessionKey, SymEncryptedProtectedData, SymKeyEncryptedSessionKey,
+    },
+    ser::Serialize,
+    types::{KeyId, PublicKeyTrait, StringToKey},
     ArmorOptions, Deserializable,
 };
 use rand::prelude::*;
@@ -8,18 +22,193 @@ use thiserror::Error as ThisError;
 
 #[derive(Debug, ThisError)]
 pub enum EncryptError {
+    #[error("No encryption capable key found for key ID {0:?}")]
+    NoEncryptionKey(KeyId),
+    #[error("Key {0:?} has expired")]
+    KeyExpired(KeyId),
+    #[error("Key {0:?} has been revoked")]
+    KeyRevoked(KeyId),
+    #[error("No recipients given")]
+    NoRecipients,
+    #[error("Password must not be empty")]
+    EmptyPassword,
+    #[error("Invalid encryption options: {0}")]
+    InvalidOptions(String),
+    #[error("Signing failed: {0}")]
+    SigningError(#[from] SigningError),
     #[error("PGP error: {0}")]
     PgpError(#[from] pgp::errors::Error),
 }
 
-/// Encrypts a message using a public key
-pub fn encrypt(msg: &str, public_key: &SignedPublicKey) -> Result<String, EncryptError> {
-    let message = Message::new_literal("none", msg);
-    let mut rng = StdRng::from_entropy();
+/// The symmetric ciphers that may be used to encrypt a message
+const SUPPORTED_CIPHERS: [SymmetricKeyAlgorithm; 7] = [
+    SymmetricKeyAlgorithm::AES128,
+    SymmetricKeyAlgorithm::AES192,
+    SymmetricKeyAlgorithm::AES256,
+    SymmetricKeyAlgorithm::Camellia128,
+    SymmetricKeyAlgorithm::Camellia192,
+    SymmetricKeyAlgorithm::Camellia256,
+    SymmetricKeyAlgorithm::Twofish,
+];
 
-    let encrypted =
-        message.encrypt_to_keys_seipdv1(&mut rng, SymmetricKeyAlgorithm::AES128, &[public_key])?;
-    Ok(encrypted.to_armored_string(ArmorOptions::default())?)
+/// The format of the encrypted data packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SeipdVersion {
+    /// SEIPDv1 with a modification detection code, readable by all OpenPGP implementations
+    #[default]
+    V1,
+    /// SEIPDv2 (RFC 9580) with authenticated encryption, only supported with AES.
+    /// Chunks are `2^(chunk_size + 6)` bytes long, `chunk_size` can be at most 16.
+    V2 { aead: AeadAlgorithm, chunk_size: u8 },
+}
+
+impl SeipdVersion {
+    /// Returns true if the cipher can be used with this format
+    fn supports(&self, cipher: SymmetricKeyAlgorithm) -> bool {
+        match self {
+            SeipdVersion::V1 => SUPPORTED_CIPHERS.contains(&cipher),
+            SeipdVersion::V2 { .. } => matches!(
+                cipher,
+                SymmetricKeyAlgorithm::AES128
+                    | SymmetricKeyAlgorithm::AES192
+                    | SymmetricKeyAlgorithm::AES256
+            ),
+        }
+    }
+}
+
+/// Options for encrypting a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EncryptOptions {
+    /// The symmetric cipher, or None to pick the first cipher in the recipients'
+    /// preferences that all of them support, falling back to AES128
+    pub cipher: Option<SymmetricKeyAlgorithm>,
+    /// The format of the encrypted data packet
+    pub version: SeipdVersion,
+}
+
+/// Returns the ciphers a key prefers, taken from its primary user ID self-signature
+fn preferred_ciphers(public_key: &SignedPublicKey) -> Vec<SymmetricKeyAlgorithm> {
+    let user = public_key
+        .details
+        .users
+        .iter()
+        .find(|user| user.is_primary())
+        .or_else(|| public_key.details.users.first());
+    user.and_then(|user| newest_self_signature(&public_key.primary_key, &user.signatures))
+        .map(|sig| sig.preferred_symmetric_algs().to_vec())
+        .unwrap_or_default()
+}
+
+/// Picks the first cipher from the first recipient's preferences that every recipient supports.
+/// AES128 is always assumed to be supported, as every implementation must support it.
+pub(crate) fn negotiate_cipher(
+    recipients: &[&SignedPublicKey],
+    version: SeipdVersion,
+) -> SymmetricKeyAlgorithm {
+    let preferences = recipients
+        .iter()
+        .map(|recipient| {
+            let mut ciphers = preferred_ciphers(recipient);
+            ciphers.push(SymmetricKeyAlgorithm::AES128);
+            ciphers
+        })
+        .collect::<Vec<_>>();
+    let Some(first) = preferences.first() else {
+        return SymmetricKeyAlgorithm::AES128;
+    };
+    first
+        .iter()
+        .copied()
+        .find(|cipher| {
+            version.supports(*cipher) && preferences.iter().all(|ciphers| ciphers.contains(cipher))
+        })
+        .unwrap_or(SymmetricKeyAlgorithm::AES128)
+}
+
+/// The key a message is actually encrypted to, either a subkey or the primary key itself
+pub(crate) enum EncryptionKey<'a> {
+    Primary(&'a packet::PublicKey),
+    Subkey(&'a packet::PublicSubkey),
+}
+
+impl EncryptionKey<'_> {
+    /// Encrypts the session key to this key, using the packet version that goes with the
+    /// encrypted data format
+    pub(crate) fn encrypt_session_key<R: CryptoRng + Rng>(
+        &self,
+        rng: &mut R,
+        session_key: &[u8],
+        alg: SymmetricKeyAlgorithm,
+        version: SeipdVersion,
+    ) -> Result<PublicKeyEncryptedSessionKey, EncryptError> {
+        let pkesk = match (self, version) {
+            (EncryptionKey::Primary(key), SeipdVersion::V1) => {
+                PublicKeyEncryptedSessionKey::from_session_key_v3(rng, session_key, alg, *key)?
+            }
+            (EncryptionKey::Subkey(key), SeipdVersion::V1) => {
+                PublicKeyEncryptedSessionKey::from_session_key_v3(rng, session_key, alg, *key)?
+            }
+            (EncryptionKey::Primary(key), SeipdVersion::V2 { .. }) => {
+                PublicKeyEncryptedSessionKey::from_session_key_v6(rng, session_key, *key)?
+            }
+            (EncryptionKey::Subkey(key), SeipdVersion::V2 { .. }) => {
+                PublicKeyEncryptedSessionKey::from_session_key_v6(rng, session_key, *key)?
+            }
+        };
+        Ok(pkesk)
+    }
+}
+
+/// Selects the key to encrypt to, preferring an unexpired and unrevoked subkey flagged for
+/// encryption. Keys without such a subkey fall back to the primary key if its algorithm can
+/// encrypt.
+pub(crate) fn encryption_key(
+    public_key: &SignedPublicKey,
+) -> Result<EncryptionKey<'_>, EncryptError> {
+    let now = Utc::now();
+    if revocation_status(public_key).is_some() {
+        return Err(EncryptError::KeyRevoked(public_key.key_id()));
+    }
+    if is_expired_at(public_key.expires_at(), now) {
+        return Err(EncryptError::KeyExpired(public_key.key_id()));
+    }
+
+    let revoked =
+        |subkey: &SignedPublicSubKey| subkey_revocation(&public_key.primary_key, subkey).is_some();
+    let mut subkeys = public_key.public_subkeys.iter().filter(|subkey| {
+        subkey.key.is_encryption_key()
+            && subkey.signatures.iter().any(|sig| {
+                let flags = sig.key_flags();
+                flags.encrypt_comms() || flags.encrypt_storage()
+            })
+    });
+    let unusable = subkeys.clone().next();
+    let subkey =
+        subkeys.find(|subkey| !revoked(subkey) && !is_expired_at(subkey_expires_at(subkey), now));
+
+    match (subkey, unusable) {
+        (Some(subkey), _) => Ok(EncryptionKey::Subkey(&subkey.key)),
+        (None, Some(revoked_subkey)) if revoked(revoked_subkey) => {
+            Err(EncryptError::KeyRevoked(revoked_subkey.key_id()))
+        }
+        (None, Some(expired)) => Err(EncryptError::KeyExpired(expired.key_id())),
+        (None, None) if public_key.primary_key.is_encryption_key() => {
+            Ok(EncryptionKey::Primary(&public_key.primary_key))
+        }
+        (None, None) => Err(EncryptError::NoEncryptionKey(public_key.key_id())),
+    }
+}
+
+/// Encrypts a message using a public key
+///
+/// The message is encrypted to the key's encryption subkey when it has one. The key can be
+/// given as a `PublicCert`, `KeyPair` or `SignedPublicKey`.
+pub fn encrypt<K>(msg: &str, public_key: &K) -> Result<String, EncryptError>
+where
+    K: PublicKeyProvider + ?Sized,
+{
+    encrypt_to_recipients(msg, &[public_key.signed_public_key()])
 }
 
 /// Encrypts a message using a public key passed as a string
@@ -28,6 +217,194 @@ pub fn encrypt_str(msg: &str, pubkey_str: &str) -> Result<String, EncryptError>
     encrypt(msg, &pubkey)
 }
 
+/// Encrypts a message so that any one of the recipients can decrypt it
+///
+/// The session key is encrypted once per recipient, to each recipient's encryption subkey
+/// when it has one. Fails if any recipient has no usable encryption key.
+pub fn encrypt_to_recipients(
+    msg: &str,
+    recipients: &[&SignedPublicKey],
+) -> Result<String, EncryptError> {
+    encrypt_with_options(msg, None, recipients, &EncryptOptions::default())
+}
+
+/// Encrypts a message with a password, for sharing with people who do not have a key
+pub fn encrypt_with_password(msg: &str, password: &str) -> Result<String, EncryptError> {
+    encrypt_with_options(msg, Some(password), &[], &EncryptOptions::default())
+}
+
+/// Encrypts a message so that it can be opened with either the password or any recipient's key
+pub fn encrypt_with_password_and_recipients(
+    msg: &str,
+    password: &str,
+    recipients: &[&SignedPublicKey],
+) -> Result<String, EncryptError> {
+    encrypt_with_options(msg, Some(password), recipients, &EncryptOptions::default())
+}
+
+/// Encrypts a message with the given cipher and packet format
+///
+/// The message gets one session key, which is encrypted with the password (if any) and
+/// to each recipient. At least a password or one recipient is required.
+pub fn encrypt_with_options(
+    msg: &str,
+    password: Option<&str>,
+    recipients: &[&SignedPublicKey],
+    options: &EncryptOptions,
+) -> Result<String, EncryptError> {
+    let message = Message::new_literal("none", msg);
+    let encrypted = encrypt_message(message, password, recipients, options)?;
+    Ok(encrypted.to_armored_string(ArmorOptions::default())?)
+}
+
+/// Encrypts arbitrary bytes to the recipients and returns the binary OpenPGP message
+///
+/// The data is stored in a binary mode literal packet, so it is decrypted byte for byte.
+pub fn encrypt_bytes(
+    data: &[u8],
+    recipients: &[&SignedPublicKey],
+) -> Result<Vec<u8>, EncryptError> {
+    encrypt_bytes_with_options(data, None, recipients, &EncryptOptions::default())
+}
+
+/// Encrypts arbitrary bytes with the given options, see `encrypt_with_options`
+pub fn encrypt_bytes_with_options(
+    data: &[u8],
+    password: Option<&str>,
+    recipients: &[&SignedPublicKey],
+    options: &EncryptOptions,
+) -> Result<Vec<u8>, EncryptError> {
+    let message = Message::new_literal_bytes("", data);
+    let encrypted = encrypt_message(message, password, recipients, options)?;
+    Ok(encrypted.to_bytes()?)
+}
+
+/// Signs data with the sender's key and encrypts the signed message to the recipients
+///
+/// The signature is inside the encryption, so only the recipients can see who sent the
+/// message. Returns the armored message, which `decrypt_and_verify` opens and checks.
+pub fn sign_and_encrypt(
+    sender: &KeyPair,
+    recipients: &[&SignedPublicKey],
+    data: &[u8],
+) -> Result<String, EncryptError> {
+    sign_and_encrypt_with_passphrase(sender, recipients, data, "")
+}
+
+/// Signs data with a passphrase protected key and encrypts the signed message to the recipients
+pub fn sign_and_encrypt_with_passphrase<P>(
+    sender: &KeyPair,
+    recipients: &[&SignedPublicKey],
+    data: &[u8],
+    passphrase: &P,
+) -> Result<String, EncryptError>
+where
+    P: PassphraseProvider + ?Sized,
+{
+    let message = Message::new_literal_bytes("", data);
+    let signed = sign_literal_message(
+        sender,
+        message,
+        &passphrase.passphrase(),
+        &SignOptions::default(),
+    )?;
+    let encrypted = encrypt_message(signed, None, recipients, &EncryptOptions::default())?;
+    Ok(encrypted.to_armored_string(ArmorOptions::default())?)
+}
+
+fn encrypt_message(
+    message: Message,
+    password: Option<&str>,
+    recipients: &[&SignedPublicKey],
+    options: &EncryptOptions,
+) -> Result<Message, EncryptError> {
+    if password.is_none() && recipients.is_empty() {
+        return Err(EncryptError::NoRecipients);
+    }
+    if password.is_some_and(str::is_empty) {
+        return Err(EncryptError::EmptyPassword);
+    }
+    let version = options.version;
+    if let SeipdVersion::V2 { chunk_size, .. } = version {
+        if chunk_size > 16 {
+            return Err(EncryptError::InvalidOptions(format!(
+                "chunk size {} is larger than 16",
+                chunk_size
+            )));
+        }
+    }
+    let alg = match options.cipher {
+        Some(alg) if !version.supports(alg) => {
+            return Err(EncryptError::InvalidOptions(format!(
+                "cipher {:?} can not be used with {:?}",
+                alg, version
+            )));
+        }
+        Some(alg) => alg,
+        None => negotiate_cipher(recipients, version),
+    };
+    let keys = recipients
+        .iter()
+        .map(|recipient| encryption_key(recipient))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut rng = StdRng::from_entropy();
+
+    let session_key = alg.new_session_key(&mut rng);
+    let mut esk = Vec::with_capacity(keys.len() + 1);
+    if let Some(password) = password {
+        let s2k = StringToKey::new_iterated(&mut rng, HashAlgorithm::SHA2_256, 224);
+        let password = || password.to_string();
+        let skesk = match version {
+            SeipdVersion::V1 => {
+                SymKeyEncryptedSessionKey::encrypt_v4(password, &session_key, s2k, alg)?
+            }
+            SeipdVersion::V2 { aead, .. } => SymKeyEncryptedSessionKey::encrypt_v6(
+                &mut rng,
+                password,
+                &session_key,
+                s2k,
+                alg,
+                aead,
+            )?,
+        };
+        esk.push(Esk::SymKeyEncryptedSessionKey(skesk));
+    }
+    for key in &keys {
+        let pkesk = key.encrypt_session_key(&mut rng, &session_key, alg, version)?;
+        esk.push(Esk::PublicKeyEncryptedSessionKey(pkesk));
+    }
+    let data = message.to_bytes()?;
+    let edata = match version {
+        SeipdVersion::V1 => {
+            SymEncryptedProtectedData::encrypt_seipdv1(&mut rng, alg, &session_key, &data)?
+        }
+        SeipdVersion::V2 { aead, chunk_size } => SymEncryptedProtectedData::encrypt_seipdv2(
+            &mut rng,
+            alg,
+            aead,
+            chunk_size,
+            &session_key,
+            &data,
+        )?,
+    };
+
+    Ok(Message::Encrypted {
+        esk,
+        edata: Edata::SymEncryptedProtectedData(edata),
+    })
+}
+
+/// Encrypts a message to several public keys passed as strings
+pub fn encrypt_to_recipients_str(msg: &str, pubkey_strs: &[&str]) -> Result<String, EncryptError> {
+    let pubkeys = pubkey_strs
+        .iter()
+        .map(|pubkey_str| Ok(SignedPublicKey::from_string(pubkey_str)?.0))
+        .collect::<Result<Vec<_>, EncryptError>>()?;
+    let recipients = pubkeys.iter().collect::<Vec<_>>();
+    encrypt_to_recipients(msg, &recipients)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,4 +468,318 @@ V2VfJKcDZiVqc9A/wOZKCLaeOJUjmeVTVPGkZrJ4hMo=
         let decrypted = decrypt::decrypt_str(encrypted.as_str(), sec_key_armored).unwrap();
         assert_eq!(decrypted, plain_msg);
     }
+
+    #[test]
+    fn test_encrypt_selects_encryption_subkey() {
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+
+        let plain_msg = "Testing testing this is a secret";
+        let key_pair = KeyPair::builder("test@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+
+        let encrypted = encrypt(plain_msg, key_pair.public_key()).unwrap();
+        let msg = Message::from_armor_single(std::io::Cursor::new(&encrypted))
+            .unwrap()
+            .0;
+        let Message::Encrypted { esk, .. } = &msg else {
+            panic!("Expected an encrypted message");
+        };
+        let pgp::composed::message::Esk::PublicKeyEncryptedSessionKey(pkesk) = &esk[0] else {
+            panic!("Expected a public key encrypted session key");
+        };
+        assert_eq!(
+            pkesk.id().unwrap(),
+            &key_pair.public_key().public_subkeys[0].key_id()
+        );
+
+        let decrypted = decrypt::decrypt(msg, key_pair.secret_key()).unwrap();
+        assert_eq!(decrypted, plain_msg);
+    }
+
+    #[test]
+    fn test_encrypt_refuses_expired_keys() {
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+        use chrono::Duration;
+
+        let plain_msg = "Testing testing this is a secret";
+        // A key created two days ago whose subkey was only valid for one day
+        let mut key_pair = KeyPair::builder("test@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .created_at(Utc::now() - Duration::days(2))
+            .expiration(Duration::days(365))
+            .subkey_expiration(Duration::days(1))
+            .build()
+            .unwrap();
+        key_pair.public_key().verify().unwrap();
+
+        let subkey_id = key_pair.public_key().public_subkeys[0].key_id();
+        assert!(matches!(
+            encrypt(plain_msg, key_pair.public_key()),
+            Err(EncryptError::KeyExpired(id)) if id == subkey_id
+        ));
+
+        // Extending the expiration makes the key usable again
+        key_pair
+            .set_expiration(Some(Utc::now() + Duration::days(30)))
+            .unwrap();
+        let encrypted = encrypt(plain_msg, key_pair.public_key()).unwrap();
+        let decrypted =
+            decrypt::decrypt_str(&encrypted, &key_pair.secret_key_armored_string().unwrap())
+                .unwrap();
+        assert_eq!(decrypted, plain_msg);
+
+        // Dates up to the creation time are rejected, but a later date that has already
+        // passed is accepted and expires the primary key right away
+        assert!(key_pair
+            .set_expiration(Some(*key_pair.public_key().created_at()))
+            .is_err());
+        key_pair
+            .set_expiration(Some(Utc::now() - Duration::hours(1)))
+            .unwrap();
+        assert!(key_pair.is_expired());
+        assert!(matches!(
+            encrypt(plain_msg, key_pair.public_key()),
+            Err(EncryptError::KeyExpired(id)) if id == key_pair.public_key().key_id()
+        ));
+    }
+
+    #[test]
+    fn test_encrypt_refuses_revoked_keys() {
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+        use crate::revocation::{apply_revocation_certificate, RevocationReason};
+        use pgp::packet::{SignatureConfig, SignatureType, Subpacket, SubpacketData};
+
+        let key_pair = KeyPair::builder("test@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+
+        let primary = &key_pair.secret_key().primary_key;
+        let mut revocation = SignatureConfig::v4(
+            SignatureType::SubkeyRevocation,
+            primary.algorithm(),
+            HashAlgorithm::SHA2_256,
+        );
+        revocation.hashed_subpackets = vec![
+            Subpacket::regular(SubpacketData::SignatureCreationTime(Utc::now())),
+            Subpacket::regular(SubpacketData::IssuerFingerprint(primary.fingerprint())),
+        ];
+        let mut public_key = key_pair.public_key().clone();
+        let subkey = &mut public_key.public_subkeys[0];
+        let revocation = revocation
+            .sign_key_binding(primary, String::new, &subkey.key)
+            .unwrap();
+        subkey.signatures.push(revocation);
+        let subkey_id = subkey.key_id();
+        assert!(matches!(
+            encrypt("secret", &public_key),
+            Err(EncryptError::KeyRevoked(id)) if id == subkey_id
+        ));
+
+        let certificate = key_pair
+            .generate_revocation_certificate(RevocationReason::KeyRetired, "")
+            .unwrap();
+        let revoked = apply_revocation_certificate(key_pair.public_key(), &certificate).unwrap();
+        assert!(matches!(
+            encrypt("secret", &revoked),
+            Err(EncryptError::KeyRevoked(id)) if id == key_pair.public_key().key_id()
+        ));
+    }
+
+    #[test]
+    fn test_encrypt_to_recipients() {
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+
+        let plain_msg = "Testing testing this is a secret";
+        let team = [KeyAlgorithm::Curve25519, KeyAlgorithm::NistP256]
+            .iter()
+            .map(|algorithm| {
+                KeyPair::builder("team@example.com")
+                    .algorithm(*algorithm)
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let escrow = KeyPair::builder("escrow@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        let outsider = KeyPair::builder("outsider@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+
+        let pubkeys = team
+            .iter()
+            .chain([&escrow])
+            .map(|key_pair| key_pair.public_key_armored_string().unwrap())
+            .collect::<Vec<_>>();
+        let pubkeys = pubkeys.iter().map(String::as_str).collect::<Vec<_>>();
+        let encrypted = encrypt_to_recipients_str(plain_msg, &pubkeys).unwrap();
+
+        let msg = Message::from_armor_single(std::io::Cursor::new(&encrypted))
+            .unwrap()
+            .0;
+        let Message::Encrypted { esk, .. } = &msg else {
+            panic!("Expected an encrypted message");
+        };
+        assert_eq!(esk.len(), 3);
+
+        for key_pair in team.iter().chain([&escrow]) {
+            let decrypted = decrypt::decrypt(msg.clone(), key_pair.secret_key()).unwrap();
+            assert_eq!(decrypted, plain_msg);
+        }
+        assert!(decrypt::decrypt(msg, outsider.secret_key()).is_err());
+
+        assert!(matches!(
+            encrypt_to_recipients(plain_msg, &[]),
+            Err(EncryptError::NoRecipients)
+        ));
+    }
+
+    #[test]
+    fn test_encrypt_with_options() {
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+        use pgp::packet::{PacketTrait, SignatureConfig, SignatureType, Subpacket, SubpacketData};
+
+        let plain_msg = "Testing testing this is a secret";
+        let key_pair = KeyPair::builder("test@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        let seipd_version = |encrypted: &str| {
+            let msg = Message::from_armor_single(std::io::Cursor::new(encrypted))
+                .unwrap()
+                .0;
+            let Message::Encrypted {
+                edata: Edata::SymEncryptedProtectedData(edata),
+                ..
+            } = msg
+            else {
+                panic!("Expected an integrity protected encrypted message");
+            };
+            edata.version()
+        };
+
+        // The key advertises AES256, which is negotiated by default
+        let encrypted = encrypt(plain_msg, key_pair.public_key()).unwrap();
+        let decrypted =
+            decrypt::decrypt_str(&encrypted, &key_pair.secret_key_armored_string().unwrap())
+                .unwrap();
+        assert_eq!(decrypted, plain_msg);
+        assert_eq!(
+            negotiate_cipher(&[key_pair.public_key()], SeipdVersion::V1),
+            SymmetricKeyAlgorithm::AES256
+        );
+
+        // A newer third-party certification does not decide the preferences
+        let other = KeyPair::builder("other@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        let mut certified = key_pair.public_key().clone();
+        let user = &mut certified.details.users[0];
+        let mut certification = SignatureConfig::v4(
+            SignatureType::CertGeneric,
+            other.algorithm(),
+            HashAlgorithm::SHA2_256,
+        );
+        certification.hashed_subpackets = vec![
+            Subpacket::regular(SubpacketData::SignatureCreationTime(
+                Utc::now() + chrono::Duration::hours(1),
+            )),
+            Subpacket::regular(SubpacketData::PreferredSymmetricAlgorithms(
+                vec![SymmetricKeyAlgorithm::Twofish].into(),
+            )),
+            Subpacket::regular(SubpacketData::IssuerFingerprint(other.fingerprint())),
+        ];
+        let certification = certification
+            .sign_certification(
+                &other.secret_key().primary_key,
+                String::new,
+                user.id.tag(),
+                &user.id,
+            )
+            .unwrap();
+        user.signatures.push(certification);
+        assert_eq!(
+            negotiate_cipher(&[&certified], SeipdVersion::V1),
+            SymmetricKeyAlgorithm::AES256
+        );
+
+        let cases = [
+            (Some(SymmetricKeyAlgorithm::Camellia256), SeipdVersion::V1),
+            (Some(SymmetricKeyAlgorithm::Twofish), SeipdVersion::V1),
+            (
+                None,
+                SeipdVersion::V2 {
+                    aead: AeadAlgorithm::Ocb,
+                    chunk_size: 6,
+                },
+            ),
+            (
+                Some(SymmetricKeyAlgorithm::AES128),
+                SeipdVersion::V2 {
+                    aead: AeadAlgorithm::Gcm,
+                    chunk_size: 0,
+                },
+            ),
+            (
+                Some(SymmetricKeyAlgorithm::AES192),
+                SeipdVersion::V2 {
+                    aead: AeadAlgorithm::Eax,
+                    chunk_size: 12,
+                },
+            ),
+        ];
+        for (cipher, version) in cases {
+            let options = EncryptOptions { cipher, version };
+            let encrypted = encrypt_with_options(
+                plain_msg,
+                Some("open sesame"),
+                &[key_pair.public_key()],
+                &options,
+            )
+            .unwrap();
+            let expected_version = match version {
+                SeipdVersion::V1 => 1,
+                SeipdVersion::V2 { .. } => 2,
+            };
+            assert_eq!(seipd_version(&encrypted), expected_version);
+
+            let msg = Message::from_armor_single(std::io::Cursor::new(&encrypted))
+                .unwrap()
+                .0;
+            assert_eq!(
+                decrypt::decrypt(msg.clone(), key_pair.secret_key()).unwrap(),
+                plain_msg
+            );
+            assert_eq!(
+                decrypt::decrypt_with_password(msg, "open sesame").unwrap(),
+                plain_msg
+            );
+        }
+
+        let options = EncryptOptions {
+            cipher: Some(SymmetricKeyAlgorithm::Twofish),
+            version: SeipdVersion::V2 {
+                aead: AeadAlgorithm::Ocb,
+                chunk_size: 6,
+            },
+        };
+        assert!(matches!(
+            encrypt_with_options(plain_msg, None, &[key_pair.public_key()], &options),
+            Err(EncryptError::InvalidOptions(_))
+        ));
+        let options = EncryptOptions {
+            cipher: Some(SymmetricKeyAlgorithm::TripleDES),
+            version: SeipdVersion::V1,
+        };
+        assert!(matches!(
+            encrypt_with_options(plain_msg, None, &[key_pair.public_key()], &options),
+            Err(EncryptError::InvalidOptions(_))
+        ));
+    }
 }
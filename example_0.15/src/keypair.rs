@@ -1,6 +1,19 @@
 //! Create PGP Key Pairs for encryption and decryption
+use crate::revocation::{self, RevocationReason};
+use chrono::{DateTime, Duration, SubsecRound, Utc};
 use os_path::OsPath;
-use pgp::types::SecretKeyTrait;
+use pgp::crypto::aead::AeadAlgorithm;
+use pgp::crypto::ecc_curve::ECCCurve;
+use pgp::crypto::hash::HashAlgorithm;
+use pgp::crypto::public_key::PublicKeyAlgorithm;
+use pgp::crypto::sym::SymmetricKeyAlgorithm;
+use pgp::packet::{
+    PacketTrait, Signature, SignatureConfig, SignatureType, Subpacket, SubpacketData,
+};
+use pgp::types::{
+    EcdhPublicParams, EcdsaPublicParams, Fingerprint, KeyId, Mpi, PublicKeyTrait, PublicParams,
+    S2kParams, SecretKeyTrait, SecretParams, StringToKey,
+};
 use pgp::{composed, crypto, Deserializable};
 use rand::prelude::*;
 use smallvec::*;
@@ -21,12 +34,610 @@ pub enum KeyPairError {
     SaveError(String),
     #[error("Failed to convert key to armored string: {0}")]
     ToArmoredStringError(String),
+    #[error("Invalid key parameters: {0}")]
+    InvalidKeyParams(String),
+    #[error("Wrong passphrase for secret key")]
+    WrongPassphrase,
+    #[error("Key has no self-signature to re-sign")]
+    MissingSelfSignature,
+    #[error("Public key {public:?} does not belong to secret key {secret:?}")]
+    KeyMismatch {
+        secret: Fingerprint,
+        public: Fingerprint,
+    },
+    #[error("Revocation error: {0}")]
+    RevocationError(#[from] revocation::RevocationError),
     #[error("PGP error: {0}")]
     PgpError(#[from] pgp::errors::Error),
     #[error("IO error context: {0}")]
     IoError(#[from] std::io::Error),
 }
 
+/// Supplies the passphrase used to unlock a protected secret key
+///
+/// Implemented for string types and for closures returning a `String`, so that
+/// a passphrase can either be passed directly or prompted for lazily.
+pub trait PassphraseProvider {
+    /// Returns the passphrase
+    fn passphrase(&self) -> String;
+}
+
+impl PassphraseProvider for str {
+    fn passphrase(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl PassphraseProvider for String {
+    fn passphrase(&self) -> String {
+        self.clone()
+    }
+}
+
+impl<F: Fn() -> String> PassphraseProvider for F {
+    fn passphrase(&self) -> String {
+        self()
+    }
+}
+
+/// Checks that the passphrase unlocks the primary key and all secret subkeys
+pub(crate) fn check_passphrase<P>(secret_key: &pgp::SignedSecretKey, passphrase: &P) -> bool
+where
+    P: PassphraseProvider + ?Sized,
+{
+    secret_key
+        .unlock(|| passphrase.passphrase(), |_| Ok(()))
+        .is_ok()
+        && secret_key.secret_subkeys.iter().all(|subkey| {
+            subkey
+                .unlock(|| passphrase.passphrase(), |_| Ok(()))
+                .is_ok()
+        })
+}
+
+/// Returns when a subkey expires according to its newest binding signature, revocations and
+/// other signatures on the subkey are skipped
+pub(crate) fn subkey_expires_at(subkey: &pgp::SignedPublicSubKey) -> Option<DateTime<Utc>> {
+    let expiration = subkey
+        .signatures
+        .iter()
+        .filter(|sig| sig.typ() == SignatureType::SubkeyBinding)
+        .max_by_key(|sig| sig.created().copied())?
+        .key_expiration_time()?;
+    Some(*subkey.key.created_at() + *expiration)
+}
+
+/// Returns true if the key had expired at the given time
+pub(crate) fn is_expired_at(expires_at: Option<DateTime<Utc>>, time: DateTime<Utc>) -> bool {
+    expires_at.is_some_and(|expires_at| expires_at <= time)
+}
+
+/// Returns the size of a key: the modulus or prime length for RSA, DSA and ElGamal keys,
+/// and the curve size for elliptic curve keys
+fn bit_length(params: &PublicParams) -> Option<usize> {
+    fn mpi_bits(mpi: &Mpi) -> usize {
+        let bytes = mpi.as_bytes();
+        bytes
+            .first()
+            .map_or(0, |first| bytes.len() * 8 - first.leading_zeros() as usize)
+    }
+    match params {
+        PublicParams::RSA { n, .. } => Some(mpi_bits(n)),
+        PublicParams::DSA { p, .. } | PublicParams::Elgamal { p, .. } => Some(mpi_bits(p)),
+        PublicParams::Ed25519 { .. } | PublicParams::X25519 { .. } => Some(255),
+        params => curve(params).map(|curve| curve.nbits() as usize),
+    }
+}
+
+/// Returns the curve of an elliptic curve key
+fn curve(params: &PublicParams) -> Option<ECCCurve> {
+    match params {
+        PublicParams::ECDSA(EcdsaPublicParams::P256 { .. }) => Some(ECCCurve::P256),
+        PublicParams::ECDSA(EcdsaPublicParams::P384 { .. }) => Some(ECCCurve::P384),
+        PublicParams::ECDSA(EcdsaPublicParams::P521 { .. }) => Some(ECCCurve::P521),
+        PublicParams::ECDSA(EcdsaPublicParams::Secp256k1 { .. }) => Some(ECCCurve::Secp256k1),
+        PublicParams::ECDSA(EcdsaPublicParams::Unsupported { curve, .. })
+        | PublicParams::ECDH(EcdhPublicParams::Known { curve, .. })
+        | PublicParams::ECDH(EcdhPublicParams::Unsupported { curve, .. })
+        | PublicParams::EdDSALegacy { curve, .. } => Some(curve.clone()),
+        _ => None,
+    }
+}
+
+/// Names a key the way `gpg --list-keys` does, such as "rsa3072" or "ed25519"
+fn algorithm_name(key: &impl PublicKeyTrait) -> String {
+    let params = key.public_params();
+    match params {
+        PublicParams::RSA { .. } | PublicParams::DSA { .. } | PublicParams::Elgamal { .. } => {
+            let prefix = match params {
+                PublicParams::RSA { .. } => "rsa",
+                PublicParams::DSA { .. } => "dsa",
+                _ => "elg",
+            };
+            format!("{}{}", prefix, bit_length(params).unwrap_or_default())
+        }
+        PublicParams::Ed25519 { .. } => "ed25519".to_string(),
+        PublicParams::X25519 { .. } => "cv25519".to_string(),
+        params => match curve(params) {
+            Some(curve) => curve.alias().unwrap_or(curve.name()).to_string(),
+            None => format!("{:?}", key.algorithm()),
+        },
+    }
+}
+
+/// Returns the fingerprint as upper case hex, as used for the keyring file names
+pub fn fingerprint_hex(fingerprint: &Fingerprint) -> String {
+    fingerprint
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect()
+}
+
+/// Returns true if a signature is a user ID certification, direct key signature or subkey
+/// binding made by the primary key, as opposed to a revocation or a third-party certification
+pub(crate) fn is_self_signature(signature: &Signature, primary: &impl PublicKeyTrait) -> bool {
+    let fingerprint = primary.fingerprint();
+    let key_id = primary.key_id();
+    matches!(
+        signature.typ(),
+        SignatureType::CertGeneric
+            | SignatureType::CertPersona
+            | SignatureType::CertCasual
+            | SignatureType::CertPositive
+            | SignatureType::Key
+            | SignatureType::SubkeyBinding
+    ) && (signature
+        .issuer_fingerprint()
+        .into_iter()
+        .any(|fp| *fp == fingerprint)
+        || signature.issuer().into_iter().any(|id| *id == key_id))
+}
+
+/// Returns the newest self-signature of a set of signatures, skipping revocations and
+/// third-party certifications
+pub(crate) fn newest_self_signature<'a>(
+    primary: &impl PublicKeyTrait,
+    signatures: impl IntoIterator<Item = &'a Signature>,
+) -> Option<&'a Signature> {
+    signatures
+        .into_iter()
+        .filter(|sig| is_self_signature(sig, primary))
+        .max_by_key(|sig| sig.created().copied())
+}
+
+/// Reads the capabilities from the key flags of the newest self-signature in a set of
+/// signatures
+fn capabilities<'a>(
+    primary: &impl PublicKeyTrait,
+    signatures: impl Iterator<Item = &'a Signature>,
+) -> KeyCapabilities {
+    let Some(signature) = newest_self_signature(primary, signatures) else {
+        return KeyCapabilities::default();
+    };
+    let flags = signature.key_flags();
+    KeyCapabilities {
+        certify: flags.certify(),
+        sign: flags.sign(),
+        encrypt: flags.encrypt_comms() || flags.encrypt_storage(),
+        authenticate: flags.authentication(),
+    }
+}
+
+/// Reads the capabilities of a primary key from its user ID and direct key self-signatures
+pub(crate) fn primary_capabilities(public_key: &pgp::SignedPublicKey) -> KeyCapabilities {
+    let details = &public_key.details;
+    capabilities(
+        &public_key.primary_key,
+        details
+            .users
+            .iter()
+            .flat_map(|user| &user.signatures)
+            .chain(&details.direct_signatures),
+    )
+}
+
+/// Reads an armored key from a file
+fn read_key_file(path: &OsPath) -> Result<String, KeyPairError> {
+    std::fs::read_to_string(path).map_err(|e| KeyPairError::LoadError {
+        file: path.clone(),
+        source: e,
+    })
+}
+
+/// Copies a self-signature's config with a fresh creation time and the given key expiration
+///
+/// The new creation time is always later than the one of the signature it replaces, so the new
+/// signature wins when a keyring merges it with an older copy of the key.
+fn with_key_expiration(signature: &Signature, expiration: Option<Duration>) -> SignatureConfig {
+    let now = Utc::now().trunc_subsecs(0);
+    let created = signature
+        .created()
+        .map_or(now, |created| now.max(*created + Duration::seconds(1)));
+    let mut config = signature.config.clone();
+    config.hashed_subpackets.retain(|subpacket| {
+        !matches!(
+            subpacket.data,
+            SubpacketData::SignatureCreationTime(_) | SubpacketData::KeyExpirationTime(_)
+        )
+    });
+    config.hashed_subpackets.insert(
+        0,
+        Subpacket::regular(SubpacketData::SignatureCreationTime(created)),
+    );
+    if let Some(expiration) = expiration {
+        config
+            .hashed_subpackets
+            .push(Subpacket::regular(SubpacketData::KeyExpirationTime(
+                expiration,
+            )));
+    }
+    config
+}
+
+/// Replaces the user ID self-signatures and subkey binding signatures of a secret key
+/// with new ones carrying the given expiration dates
+///
+/// Each new signature copies the newest self-signature it supersedes. Revocations and
+/// third-party certifications are kept, so a revoked subkey stays revoked.
+fn resign_with_expiration(
+    secret_key: &pgp::SignedSecretKey,
+    key_expires_at: Option<DateTime<Utc>>,
+    subkey_expires_at: Option<DateTime<Utc>>,
+    passphrase: &str,
+) -> Result<pgp::SignedSecretKey, KeyPairError> {
+    // The expiration subpacket is relative to the creation time of the key it applies to
+    let expiration = |created_at: &DateTime<Utc>, expires_at: Option<DateTime<Utc>>| {
+        expires_at.map(|expires_at| (expires_at - *created_at).max(Duration::seconds(1)))
+    };
+    let mut resigned = secret_key.clone();
+    let primary_key = &secret_key.primary_key;
+    let replace = |signatures: &mut Vec<Signature>, signature: Signature| {
+        signatures.retain(|sig| !is_self_signature(sig, primary_key));
+        signatures.push(signature);
+    };
+    let key_expiration = expiration(primary_key.created_at(), key_expires_at);
+
+    for user in resigned.details.users.iter_mut() {
+        let signature = newest_self_signature(primary_key, &user.signatures)
+            .ok_or(KeyPairError::MissingSelfSignature)?;
+        let signature = with_key_expiration(signature, key_expiration).sign_certification(
+            primary_key,
+            || passphrase.to_string(),
+            user.id.tag(),
+            &user.id,
+        )?;
+        replace(&mut user.signatures, signature);
+    }
+
+    for subkey in resigned.secret_subkeys.iter_mut() {
+        let signature = newest_self_signature(primary_key, &subkey.signatures)
+            .ok_or(KeyPairError::MissingSelfSignature)?;
+        let subkey_expiration = expiration(subkey.key.created_at(), subkey_expires_at);
+        let signature = with_key_expiration(signature, subkey_expiration).sign_key_binding(
+            primary_key,
+            || passphrase.to_string(),
+            &subkey.key,
+        )?;
+        replace(&mut subkey.signatures, signature);
+    }
+
+    for subkey in resigned.public_subkeys.iter_mut() {
+        let signature = newest_self_signature(primary_key, &subkey.signatures)
+            .ok_or(KeyPairError::MissingSelfSignature)?;
+        let subkey_expiration = expiration(subkey.key.created_at(), subkey_expires_at);
+        let signature = with_key_expiration(signature, subkey_expiration).sign_key_binding(
+            primary_key,
+            || passphrase.to_string(),
+            &subkey.key,
+        )?;
+        replace(&mut subkey.signatures, signature);
+    }
+
+    Ok(resigned)
+}
+
+/// The string-to-key method used to derive the encryption key for a passphrase protected secret key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum S2kMode {
+    /// Iterated and salted SHA2-256 with AES256 in CFB mode, readable by all common implementations
+    #[default]
+    IteratedSha256,
+    /// Argon2 (RFC 9580) with AES256 in OCB mode, memory-hard but not supported by older implementations
+    Argon2,
+}
+
+impl S2kMode {
+    /// Creates fresh S2K parameters with a random salt and IV
+    fn params<R: Rng + CryptoRng>(&self, rng: &mut R) -> S2kParams {
+        let sym_alg = SymmetricKeyAlgorithm::AES256;
+        match self {
+            S2kMode::IteratedSha256 => {
+                let mut iv = vec![0u8; sym_alg.block_size()];
+                rng.fill(&mut iv[..]);
+                S2kParams::Cfb {
+                    sym_alg,
+                    s2k: StringToKey::new_iterated(&mut *rng, HashAlgorithm::SHA2_256, 224),
+                    iv,
+                }
+            }
+            S2kMode::Argon2 => {
+                let aead_mode = AeadAlgorithm::Ocb;
+                let mut nonce = vec![0u8; aead_mode.nonce_size()];
+                rng.fill(&mut nonce[..]);
+                // parameter choice (2) from RFC 9106: t = 3, p = 4, m = 64 MiB
+                S2kParams::Aead {
+                    sym_alg,
+                    aead_mode,
+                    s2k: StringToKey::new_argon2(&mut *rng, 3, 4, 16),
+                    nonce,
+                }
+            }
+        }
+    }
+}
+
+/// The public key algorithm used when generating a new key pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyAlgorithm {
+    /// RSA with a 2048 bit modulus
+    #[default]
+    Rsa2048,
+    /// RSA with a 3072 bit modulus
+    Rsa3072,
+    /// RSA with a 4096 bit modulus
+    Rsa4096,
+    /// EdDSA (legacy format) signing key with an ECDH Curve25519 encryption subkey
+    Curve25519,
+    /// ECDSA NIST P-256 signing key with an ECDH NIST P-256 encryption subkey
+    NistP256,
+    /// ECDSA NIST P-384 signing key with an ECDH NIST P-384 encryption subkey
+    NistP384,
+}
+
+impl KeyAlgorithm {
+    /// The key type used for the primary (signing) key
+    fn primary_key_type(&self) -> composed::KeyType {
+        match self {
+            KeyAlgorithm::Rsa2048 => composed::KeyType::Rsa(2048),
+            KeyAlgorithm::Rsa3072 => composed::KeyType::Rsa(3072),
+            KeyAlgorithm::Rsa4096 => composed::KeyType::Rsa(4096),
+            KeyAlgorithm::Curve25519 => composed::KeyType::EdDSALegacy,
+            KeyAlgorithm::NistP256 => composed::KeyType::ECDSA(ECCCurve::P256),
+            KeyAlgorithm::NistP384 => composed::KeyType::ECDSA(ECCCurve::P384),
+        }
+    }
+
+    /// The key type used for the encryption subkey
+    fn encryption_subkey_type(&self) -> composed::KeyType {
+        match self {
+            KeyAlgorithm::Rsa2048 => composed::KeyType::Rsa(2048),
+            KeyAlgorithm::Rsa3072 => composed::KeyType::Rsa(3072),
+            KeyAlgorithm::Rsa4096 => composed::KeyType::Rsa(4096),
+            KeyAlgorithm::Curve25519 => composed::KeyType::ECDH(ECCCurve::Curve25519),
+            KeyAlgorithm::NistP256 => composed::KeyType::ECDH(ECCCurve::P256),
+            KeyAlgorithm::NistP384 => composed::KeyType::ECDH(ECCCurve::P384),
+        }
+    }
+
+    /// The key type used for the optional authentication subkey
+    fn authentication_subkey_type(&self) -> composed::KeyType {
+        self.primary_key_type()
+    }
+}
+
+/// Builder for generating a new KeyPair with non-default options
+///
+/// The generated key follows the usual GnuPG layout: a primary key that can certify and sign,
+/// a dedicated encryption subkey, and optionally an authentication subkey.
+///
+/// ```no_run
+/// use create_keys_and_use::keypair::{KeyAlgorithm, KeyPairBuilder};
+///
+/// let key_pair = KeyPairBuilder::new("alice@example.com")
+///     .algorithm(KeyAlgorithm::Curve25519)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyPairBuilder {
+    user_id: String,
+    algorithm: KeyAlgorithm,
+    authentication_subkey: bool,
+    passphrase: Option<String>,
+    s2k: S2kMode,
+    expiration: Option<Duration>,
+    subkey_expiration: Option<Duration>,
+    created_at: Option<DateTime<Utc>>,
+}
+
+impl KeyPairBuilder {
+    /// Creates a new builder for the given user ID, using the default key algorithm
+    pub fn new(user_id: &str) -> Self {
+        KeyPairBuilder {
+            user_id: user_id.to_string(),
+            algorithm: KeyAlgorithm::default(),
+            authentication_subkey: false,
+            passphrase: None,
+            s2k: S2kMode::default(),
+            expiration: None,
+            subkey_expiration: None,
+            created_at: None,
+        }
+    }
+
+    /// Sets the key algorithm
+    pub fn algorithm(mut self, algorithm: KeyAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets whether an authentication subkey (e.g. for SSH) is generated as well
+    pub fn authentication_subkey(mut self, authentication_subkey: bool) -> Self {
+        self.authentication_subkey = authentication_subkey;
+        self
+    }
+
+    /// Locks the generated secret key and subkeys with a passphrase
+    pub fn passphrase(mut self, passphrase: &str) -> Self {
+        self.passphrase = Some(passphrase.to_string());
+        self
+    }
+
+    /// Sets how the passphrase is turned into a key, only used together with a passphrase
+    pub fn s2k(mut self, s2k: S2kMode) -> Self {
+        self.s2k = s2k;
+        self
+    }
+
+    /// Sets how long the key is valid after creation, applies to the subkeys as well
+    /// unless `subkey_expiration` is set
+    pub fn expiration(mut self, expiration: Duration) -> Self {
+        self.expiration = Some(expiration);
+        self
+    }
+
+    /// Sets how long the subkeys are valid after creation
+    pub fn subkey_expiration(mut self, expiration: Duration) -> Self {
+        self.subkey_expiration = Some(expiration);
+        self
+    }
+
+    /// Sets the creation time of the key and its subkeys instead of the current time
+    pub fn created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Generates the KeyPair
+    pub fn build(self) -> Result<KeyPair, KeyPairError> {
+        let subkey_expiration = self.subkey_expiration.or(self.expiration);
+        for expiration in [self.expiration, subkey_expiration].into_iter().flatten() {
+            if expiration <= Duration::zero() {
+                return Err(KeyPairError::InvalidKeyParams(
+                    "Expiration must be a positive duration".to_string(),
+                ));
+            }
+        }
+
+        let created_at = self.created_at.unwrap_or_else(Utc::now).trunc_subsecs(0);
+        let mut rng = StdRng::from_entropy();
+        let mut key_params = composed::key::SecretKeyParamsBuilder::default();
+        key_params
+            .key_type(self.algorithm.primary_key_type())
+            .created_at(created_at)
+            .can_certify(true)
+            .can_sign(true)
+            .primary_user_id(self.user_id)
+            .preferred_symmetric_algorithms(smallvec![crypto::sym::SymmetricKeyAlgorithm::AES256])
+            .passphrase(self.passphrase.clone())
+            .s2k(Some(self.s2k.params(&mut rng)));
+
+        let encryption_subkey = composed::key::SubkeyParamsBuilder::default()
+            .key_type(self.algorithm.encryption_subkey_type())
+            .can_encrypt(true)
+            .passphrase(self.passphrase.clone())
+            .s2k(Some(self.s2k.params(&mut rng)))
+            .created_at(created_at)
+            .build()
+            .map_err(|e| KeyPairError::InvalidKeyParams(e.to_string()))?;
+        key_params.subkey(encryption_subkey);
+
+        if self.authentication_subkey {
+            let authentication_subkey = composed::key::SubkeyParamsBuilder::default()
+                .key_type(self.algorithm.authentication_subkey_type())
+                .can_authenticate(true)
+                .passphrase(self.passphrase.clone())
+                .s2k(Some(self.s2k.params(&mut rng)))
+                .created_at(created_at)
+                .build()
+                .map_err(|e| KeyPairError::InvalidKeyParams(e.to_string()))?;
+            key_params.subkey(authentication_subkey);
+        }
+
+        let secret_key_params = key_params
+            .build()
+            .map_err(|e| KeyPairError::InvalidKeyParams(e.to_string()))?;
+
+        let secret_key = secret_key_params.generate(&mut rng)?;
+
+        let passphrase = self.passphrase.unwrap_or_default();
+        let passwd_fn = || passphrase.clone();
+        let mut signed_secret_key = secret_key.sign(&mut rng, passwd_fn)?;
+
+        // rpgp does not write an expiration into v4 self-signatures, so add it by re-signing
+        if self.expiration.is_some() || subkey_expiration.is_some() {
+            signed_secret_key = resign_with_expiration(
+                &signed_secret_key,
+                self.expiration.map(|expiration| created_at + expiration),
+                subkey_expiration.map(|expiration| created_at + expiration),
+                &passphrase,
+            )?;
+        }
+
+        // The public key carries the same self-signatures as the secret key
+        let signed_public_key = pgp::SignedPublicKey::from(signed_secret_key.clone());
+        Ok(KeyPair {
+            secret_key: signed_secret_key,
+            public_key: signed_public_key,
+        })
+    }
+
+    /// Generates the KeyPair together with an armored revocation certificate for it
+    ///
+    /// The certificate gives no reason for the revocation. Store it offline, so the key can still
+    /// be revoked with `revocation::apply_revocation_certificate` if the secret key is lost.
+    pub fn build_with_revocation_certificate(self) -> Result<(KeyPair, String), KeyPairError> {
+        let passphrase = self.passphrase.clone().unwrap_or_default();
+        let key_pair = self.build()?;
+        let certificate = key_pair.generate_revocation_certificate_with_passphrase(
+            RevocationReason::NoReason,
+            "",
+            passphrase.as_str(),
+        )?;
+        Ok((key_pair, certificate))
+    }
+}
+
+/// What a key or subkey may be used for, according to the key flags of its self-signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyCapabilities {
+    pub certify: bool,
+    pub sign: bool,
+    pub encrypt: bool,
+    pub authenticate: bool,
+}
+
+impl std::fmt::Display for KeyCapabilities {
+    /// Writes the capabilities as letters, "SCEA" order as in `gpg --list-keys`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (capable, letter) in [
+            (self.sign, 'S'),
+            (self.certify, 'C'),
+            (self.encrypt, 'E'),
+            (self.authenticate, 'A'),
+        ] {
+            if capable {
+                write!(f, "{}", letter)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The details of a subkey, see `KeyPair::subkeys`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubkeyInfo {
+    pub fingerprint: Fingerprint,
+    pub key_id: KeyId,
+    pub algorithm: PublicKeyAlgorithm,
+    /// The key size in bits, None for an unknown algorithm
+    pub bit_length: Option<usize>,
+    pub creation_time: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub capabilities: KeyCapabilities,
+}
+
 /// A struct that contains a public and private key pair
 #[derive(Debug)]
 pub struct KeyPair {
@@ -36,77 +647,178 @@ pub struct KeyPair {
 
 impl KeyPair {
     /// Creates a KeyPair from armored string representations of the secret and public keys
+    ///
+    /// Fails with `KeyMismatch` if the public key does not belong to the secret key.
     pub fn from_armored_strings(secret_key: &str, public_key: &str) -> Result<Self, KeyPairError> {
         let (secret_key, _) = pgp::SignedSecretKey::from_string(secret_key)
             .map_err(|e| KeyPairError::FromStringError(e.to_string()))?;
         let (public_key, _) = pgp::SignedPublicKey::from_string(public_key)
             .map_err(|e| KeyPairError::FromStringError(e.to_string()))?;
 
+        if secret_key.fingerprint() != public_key.fingerprint() {
+            return Err(KeyPairError::KeyMismatch {
+                secret: secret_key.fingerprint(),
+                public: public_key.fingerprint(),
+            });
+        }
+
         Ok(KeyPair {
             secret_key,
             public_key,
         })
     }
 
+    /// Creates a KeyPair from an armored secret key alone, deriving the public key from it
+    ///
+    /// The public key carries the same user IDs, subkeys and self-signatures as the secret key.
+    pub fn from_secret_key_armored(secret_key: &str) -> Result<Self, KeyPairError> {
+        let (secret_key, _) = pgp::SignedSecretKey::from_string(secret_key)
+            .map_err(|e| KeyPairError::FromStringError(e.to_string()))?;
+        Ok(Self::from_secret_key(secret_key))
+    }
+
+    /// Creates a KeyPair from a parsed secret key, deriving the public key from it
+    pub(crate) fn from_secret_key(secret_key: pgp::SignedSecretKey) -> Self {
+        let public_key = pgp::SignedPublicKey::from(secret_key.clone());
+        KeyPair {
+            secret_key,
+            public_key,
+        }
+    }
+
     /// Creates a KeyPair by loading the secret and public keys from files
+    ///
+    /// Fails with `KeyMismatch` if the public key does not belong to the secret key.
     pub fn from_files(
         secret_key_path: &OsPath,
         public_key_path: &OsPath,
     ) -> Result<Self, KeyPairError> {
-        let secret_key =
-            std::fs::read_to_string(&secret_key_path).map_err(|e| KeyPairError::LoadError {
-                file: secret_key_path.clone(),
-                source: e,
-            })?;
-        let public_key =
-            std::fs::read_to_string(&public_key_path).map_err(|e| KeyPairError::LoadError {
-                file: public_key_path.clone(),
-                source: e,
-            })?;
+        let secret_key = read_key_file(secret_key_path)?;
+        let public_key = read_key_file(public_key_path)?;
         Self::from_armored_strings(&secret_key, &public_key)
     }
 
+    /// Creates a KeyPair by loading the secret key from a file, deriving the public key from it
+    pub fn from_secret_key_file(secret_key_path: &OsPath) -> Result<Self, KeyPairError> {
+        Self::from_secret_key_armored(&read_key_file(secret_key_path)?)
+    }
+
     /// Generates a new KeyPair with default parameters
     /// # Arguments
     /// * `user_id` - The user ID to associate with the key pair, can be anything you want, typicall an email address
-    #[allow(clippy::redundant_closure)]
     pub fn generate_key_pair(user_id: &str) -> Self {
-        let mut key_params = composed::key::SecretKeyParamsBuilder::default();
-        key_params
-            .key_type(composed::KeyType::Rsa(2048))
-            .can_certify(false)
-            .can_sign(true)
-            .primary_user_id(user_id.into())
-            .preferred_symmetric_algorithms(smallvec![crypto::sym::SymmetricKeyAlgorithm::AES256]);
-
-        let secret_key_params = key_params
+        KeyPairBuilder::new(user_id)
             .build()
-            .expect("Must be able to create secret key params");
+            .expect("Must be able to generate a key pair with default parameters")
+    }
+
+    /// Returns a builder for generating a KeyPair with non-default options
+    pub fn builder(user_id: &str) -> KeyPairBuilder {
+        KeyPairBuilder::new(user_id)
+    }
 
-        let rng = StdRng::from_entropy();
-        let secret_key = secret_key_params
-            .generate(rng)
-            .expect("Failed to generate a plain key.");
+    /// Returns true if the secret key is locked with a passphrase
+    pub fn is_passphrase_protected(&self) -> bool {
+        matches!(
+            self.secret_key.primary_key.secret_params(),
+            SecretParams::Encrypted(_)
+        )
+    }
 
-        let rng = StdRng::from_entropy();
-        let passwd_fn = || String::new();
-        let signed_secret_key = secret_key
-            .sign(rng, passwd_fn)
-            .expect("Secret Key must be able to sign its own metadata");
+    /// Checks that the passphrase unlocks the secret key
+    pub fn verify_passphrase<P>(&self, passphrase: &P) -> Result<(), KeyPairError>
+    where
+        P: PassphraseProvider + ?Sized,
+    {
+        if check_passphrase(&self.secret_key, passphrase) {
+            Ok(())
+        } else {
+            Err(KeyPairError::WrongPassphrase)
+        }
+    }
 
-        let rng = StdRng::from_entropy();
-        let public_key = signed_secret_key.public_key();
-        let signed_public_key = public_key
-            .sign(rng, &signed_secret_key, passwd_fn)
-            .expect("Public key must be able to sign its own metadata");
+    /// Returns when the primary key expires, or None if it never does
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.public_key.expires_at()
+    }
 
-        KeyPair {
-            secret_key: signed_secret_key,
-            public_key: signed_public_key,
+    /// Returns true if the primary key has expired
+    pub fn is_expired(&self) -> bool {
+        is_expired_at(self.expires_at(), Utc::now())
+    }
+
+    /// Sets the expiration date of the primary key and all subkeys by re-signing the
+    /// self-signatures, `None` removes the expiration
+    ///
+    /// This can both extend and shorten the validity period. Share the updated public key
+    /// so that others see the new expiration date.
+    pub fn set_expiration(
+        &mut self,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), KeyPairError> {
+        self.set_expiration_with_passphrase(expires_at, "")
+    }
+
+    /// Sets the expiration date of a passphrase protected key, see `set_expiration`
+    pub fn set_expiration_with_passphrase<P>(
+        &mut self,
+        expires_at: Option<DateTime<Utc>>,
+        passphrase: &P,
+    ) -> Result<(), KeyPairError>
+    where
+        P: PassphraseProvider + ?Sized,
+    {
+        if expires_at.is_some_and(|expires_at| expires_at <= *self.secret_key.created_at()) {
+            return Err(KeyPairError::InvalidKeyParams(
+                "Expiration must be after the key creation time".to_string(),
+            ));
         }
+
+        let passphrase = passphrase.passphrase();
+        self.verify_passphrase(passphrase.as_str())?;
+
+        let secret_key =
+            resign_with_expiration(&self.secret_key, expires_at, expires_at, &passphrase)?;
+        self.public_key = pgp::SignedPublicKey::from(secret_key.clone());
+        self.secret_key = secret_key;
+        Ok(())
+    }
+
+    /// Creates an armored revocation certificate for this key
+    ///
+    /// Generate it right after creating the key and store it offline. Applying it with
+    /// `revocation::apply_revocation_certificate` marks the public key as revoked.
+    pub fn generate_revocation_certificate(
+        &self,
+        reason: RevocationReason,
+        description: &str,
+    ) -> Result<String, KeyPairError> {
+        self.generate_revocation_certificate_with_passphrase(reason, description, "")
+    }
+
+    /// Creates an armored revocation certificate for a passphrase protected key
+    pub fn generate_revocation_certificate_with_passphrase<P>(
+        &self,
+        reason: RevocationReason,
+        description: &str,
+        passphrase: &P,
+    ) -> Result<String, KeyPairError>
+    where
+        P: PassphraseProvider + ?Sized,
+    {
+        let passphrase = passphrase.passphrase();
+        self.verify_passphrase(passphrase.as_str())?;
+        Ok(revocation::generate_revocation_certificate(
+            &self.secret_key,
+            &passphrase,
+            reason,
+            description,
+        )?)
     }
 
     /// Saves the KeyPair to the specified directory as "secret_key.asc" and "public_key.asc"
+    ///
+    /// A passphrase protected secret key is saved in its locked form.
     pub fn save(&self, save_directory: &OsPath) -> Result<(), KeyPairError> {
         let ao = pgp::ArmorOptions {
             headers: None,
@@ -133,6 +845,110 @@ impl KeyPair {
         &self.public_key
     }
 
+    /// Returns the fingerprint of the primary key
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.public_key.fingerprint()
+    }
+
+    /// Returns the long key ID of the primary key
+    pub fn key_id(&self) -> KeyId {
+        self.public_key.key_id()
+    }
+
+    /// Returns the user IDs of the key, such as "Alice <alice@example.com>"
+    pub fn user_ids(&self) -> Vec<String> {
+        self.public_key
+            .details
+            .users
+            .iter()
+            .map(|user| String::from_utf8_lossy(user.id.id()).into_owned())
+            .collect()
+    }
+
+    /// Returns when the primary key was created
+    pub fn creation_time(&self) -> DateTime<Utc> {
+        *self.public_key.created_at()
+    }
+
+    /// Returns the public key algorithm of the primary key
+    pub fn algorithm(&self) -> PublicKeyAlgorithm {
+        self.public_key.algorithm()
+    }
+
+    /// Returns the size of the primary key in bits, the curve size for elliptic curve keys
+    pub fn bit_length(&self) -> Option<usize> {
+        bit_length(self.public_key.public_params())
+    }
+
+    /// Returns what the primary key may be used for
+    pub fn capabilities(&self) -> KeyCapabilities {
+        primary_capabilities(&self.public_key)
+    }
+
+    /// Returns the subkeys with what each of them may be used for
+    pub fn subkeys(&self) -> Vec<SubkeyInfo> {
+        self.public_key
+            .public_subkeys
+            .iter()
+            .map(|subkey| SubkeyInfo {
+                fingerprint: subkey.key.fingerprint(),
+                key_id: subkey.key.key_id(),
+                algorithm: subkey.key.algorithm(),
+                bit_length: bit_length(subkey.key.public_params()),
+                creation_time: *subkey.key.created_at(),
+                expires_at: subkey_expires_at(subkey),
+                capabilities: capabilities(&self.public_key.primary_key, subkey.signatures.iter()),
+            })
+            .collect()
+    }
+
+    /// Returns a description of the key in the format of `gpg --list-keys`
+    ///
+    /// ```text
+    /// pub   ed25519 2025-01-31 [SC] [expires: 2026-01-31]
+    ///       9F4A3C0B1D2E5F6A7B8C9D0E1F2A3B4C5D6E7F80
+    /// uid           Alice <alice@example.com>
+    /// sub   cv25519 2025-01-31 [E]
+    /// ```
+    pub fn summary(&self) -> String {
+        let now = Utc::now();
+        let validity = |expires_at: Option<DateTime<Utc>>| match expires_at {
+            Some(expires_at) if is_expired_at(Some(expires_at), now) => {
+                format!(" [expired: {}]", expires_at.format("%Y-%m-%d"))
+            }
+            Some(expires_at) => format!(" [expires: {}]", expires_at.format("%Y-%m-%d")),
+            None => String::new(),
+        };
+
+        let revoked = if revocation::revocation_status(&self.public_key).is_some() {
+            " [revoked]"
+        } else {
+            ""
+        };
+        let mut summary = format!(
+            "pub   {} {} [{}]{}{}\n      {}\n",
+            algorithm_name(&self.public_key.primary_key),
+            self.creation_time().format("%Y-%m-%d"),
+            self.capabilities(),
+            revoked,
+            validity(self.expires_at()),
+            fingerprint_hex(&self.fingerprint()),
+        );
+        for user_id in self.user_ids() {
+            summary.push_str(&format!("uid           {}\n", user_id));
+        }
+        for (subkey, info) in self.public_key.public_subkeys.iter().zip(self.subkeys()) {
+            summary.push_str(&format!(
+                "sub   {} {} [{}]{}\n",
+                algorithm_name(&subkey.key),
+                info.creation_time.format("%Y-%m-%d"),
+                info.capabilities,
+                validity(info.expires_at),
+            ));
+        }
+        summary
+    }
+
     /// Returns the armored string representation of the public key
     pub fn public_key_armored_string(&self) -> Result<String, KeyPairError> {
         let ao = pgp::ArmorOptions {
@@ -171,5 +987,334 @@ mod tests {
         let key_pair2 = KeyPair::from_armored_strings(&sec_ascii, &pub_ascii).unwrap();
         assert_eq!(key_pair.secret_key, key_pair2.secret_key);
         assert_eq!(key_pair.public_key, key_pair2.public_key);
+        assert_eq!(
+            KeyPair::from_secret_key_armored(&sec_ascii)
+                .unwrap()
+                .public_key,
+            key_pair.public_key
+        );
+        assert_eq!(key_pair.bit_length(), Some(2048));
+        assert!(key_pair.summary().starts_with("pub   rsa2048 "));
+    }
+
+    #[test]
+    fn test_key_pair_from_secret_key() {
+        let key_pair = KeyPair::builder("foo bar baz")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .passphrase("correct horse")
+            .build()
+            .unwrap();
+        let pub_ascii = key_pair.public_key_armored_string().unwrap();
+        let sec_ascii = key_pair.secret_key_armored_string().unwrap();
+
+        // The derived public key matches the one generated with the secret key
+        let key_pair2 = KeyPair::from_secret_key_armored(&sec_ascii).unwrap();
+        key_pair2.public_key.verify().unwrap();
+        assert_eq!(key_pair.public_key, key_pair2.public_key);
+        assert_eq!(key_pair.expires_at(), key_pair2.expires_at());
+        key_pair2.verify_passphrase("correct horse").unwrap();
+
+        let directory = crate::test_util::TestDir::new("test_key_pair_from_secret_key");
+        let save_directory = OsPath::from(directory.path());
+        key_pair.save(&save_directory).unwrap();
+        let key_pair3 =
+            KeyPair::from_secret_key_file(&save_directory.join("secret_key.asc")).unwrap();
+        assert_eq!(key_pair.public_key, key_pair3.public_key);
+
+        // A public key that belongs to another secret key is rejected
+        let other = KeyPair::builder("other")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        let other_pub_ascii = other.public_key_armored_string().unwrap();
+        assert!(matches!(
+            KeyPair::from_armored_strings(&sec_ascii, &other_pub_ascii),
+            Err(KeyPairError::KeyMismatch { .. })
+        ));
+        std::fs::write(save_directory.join("public_key.asc"), &other_pub_ascii).unwrap();
+        assert!(matches!(
+            KeyPair::from_files(
+                &save_directory.join("secret_key.asc"),
+                &save_directory.join("public_key.asc")
+            ),
+            Err(KeyPairError::KeyMismatch { .. })
+        ));
+        KeyPair::from_armored_strings(&sec_ascii, &pub_ascii).unwrap();
+    }
+
+    #[test]
+    fn test_key_pair_algorithms() {
+        for algorithm in [
+            KeyAlgorithm::Curve25519,
+            KeyAlgorithm::NistP256,
+            KeyAlgorithm::NistP384,
+        ] {
+            let key_pair = KeyPair::builder("foo bar baz")
+                .algorithm(algorithm)
+                .build()
+                .unwrap();
+            assert_eq!(key_pair.secret_key.secret_subkeys.len(), 1);
+            assert!(key_pair.secret_key.secret_subkeys[0].signatures[0]
+                .key_flags()
+                .encrypt_comms());
+            key_pair.secret_key.verify().unwrap();
+            key_pair.public_key.verify().unwrap();
+
+            let pub_ascii = key_pair.public_key_armored_string().unwrap();
+            let sec_ascii = key_pair.secret_key_armored_string().unwrap();
+            let key_pair2 = KeyPair::from_armored_strings(&sec_ascii, &pub_ascii).unwrap();
+            assert_eq!(key_pair.secret_key, key_pair2.secret_key);
+        }
+    }
+
+    #[test]
+    fn test_key_pair_passphrase() {
+        for s2k in [S2kMode::IteratedSha256, S2kMode::Argon2] {
+            let key_pair = KeyPair::builder("foo bar baz")
+                .algorithm(KeyAlgorithm::Curve25519)
+                .passphrase("correct horse")
+                .s2k(s2k)
+                .build()
+                .unwrap();
+            key_pair.public_key.verify().unwrap();
+            assert!(key_pair.is_passphrase_protected());
+            key_pair.verify_passphrase("correct horse").unwrap();
+            assert!(matches!(
+                key_pair.verify_passphrase("battery staple"),
+                Err(KeyPairError::WrongPassphrase)
+            ));
+
+            // The armored secret key stays locked
+            let pub_ascii = key_pair.public_key_armored_string().unwrap();
+            let sec_ascii = key_pair.secret_key_armored_string().unwrap();
+            let key_pair2 = KeyPair::from_armored_strings(&sec_ascii, &pub_ascii).unwrap();
+            assert!(key_pair2.is_passphrase_protected());
+            key_pair2.verify_passphrase("correct horse").unwrap();
+        }
+
+        let key_pair = KeyPair::builder("foo bar baz")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        assert!(!key_pair.is_passphrase_protected());
+    }
+
+    #[test]
+    fn test_key_pair_expiration() {
+        let mut key_pair = KeyPair::builder("foo bar baz")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .expiration(Duration::days(365))
+            .subkey_expiration(Duration::days(30))
+            .build()
+            .unwrap();
+        key_pair.public_key.verify().unwrap();
+        let created_at = *key_pair.public_key.created_at();
+        assert_eq!(
+            key_pair.expires_at(),
+            Some(created_at + Duration::days(365))
+        );
+        assert_eq!(
+            subkey_expires_at(&key_pair.public_key.public_subkeys[0]),
+            Some(created_at + Duration::days(30))
+        );
+        assert!(!key_pair.is_expired());
+
+        // Removing the expiration re-signs the key without one
+        key_pair.set_expiration(None).unwrap();
+        key_pair.public_key.verify().unwrap();
+        assert_eq!(key_pair.expires_at(), None);
+        assert_eq!(
+            subkey_expires_at(&key_pair.public_key.public_subkeys[0]),
+            None
+        );
+
+        let pub_ascii = key_pair.public_key_armored_string().unwrap();
+        let sec_ascii = key_pair.secret_key_armored_string().unwrap();
+        let key_pair2 = KeyPair::from_armored_strings(&sec_ascii, &pub_ascii).unwrap();
+        assert_eq!(key_pair.public_key, key_pair2.public_key);
+
+        assert!(matches!(
+            KeyPair::builder("foo bar baz")
+                .algorithm(KeyAlgorithm::Curve25519)
+                .expiration(Duration::zero())
+                .build(),
+            Err(KeyPairError::InvalidKeyParams(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_expiration_keeps_revocations() {
+        let key_pair = KeyPair::builder("foo bar baz")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .expiration(Duration::days(365))
+            .build()
+            .unwrap();
+        let other = KeyPair::builder("other").build().unwrap();
+        let mut secret_key = key_pair.secret_key.clone();
+        let primary = &key_pair.secret_key.primary_key;
+
+        let user = &mut secret_key.details.users[0];
+        let mut certification = SignatureConfig::v4(
+            SignatureType::CertGeneric,
+            other.algorithm(),
+            HashAlgorithm::SHA2_256,
+        );
+        certification.hashed_subpackets = vec![
+            Subpacket::regular(SubpacketData::SignatureCreationTime(
+                key_pair.creation_time() + Duration::hours(1),
+            )),
+            Subpacket::regular(SubpacketData::IssuerFingerprint(other.fingerprint())),
+        ];
+        let certification = certification
+            .sign_certification(
+                &other.secret_key.primary_key,
+                String::new,
+                user.id.tag(),
+                &user.id,
+            )
+            .unwrap();
+        user.signatures.push(certification.clone());
+
+        let subkey = &mut secret_key.secret_subkeys[0];
+        let mut revocation = SignatureConfig::v4(
+            SignatureType::SubkeyRevocation,
+            primary.algorithm(),
+            HashAlgorithm::SHA2_256,
+        );
+        revocation.hashed_subpackets = vec![
+            Subpacket::regular(SubpacketData::SignatureCreationTime(
+                key_pair.creation_time() + Duration::hours(1),
+            )),
+            Subpacket::regular(SubpacketData::IssuerFingerprint(primary.fingerprint())),
+        ];
+        let revocation = revocation
+            .sign_key_binding(primary, String::new, &subkey.key)
+            .unwrap();
+        subkey.signatures.push(revocation.clone());
+
+        // The newer revocation is not mistaken for a binding without an expiration
+        let mut key_pair = KeyPair::from_secret_key(secret_key);
+        let subkey = &key_pair.public_key.public_subkeys[0];
+        assert_eq!(
+            subkey_expires_at(subkey),
+            Some(key_pair.creation_time() + Duration::days(365))
+        );
+
+        let expires_at = (Utc::now() + Duration::days(30)).trunc_subsecs(0);
+        key_pair.set_expiration(Some(expires_at)).unwrap();
+        assert_eq!(key_pair.expires_at(), Some(expires_at));
+
+        // Only the superseded self-signatures are replaced, and the new ones are copied from
+        // self-signatures, not from the certification or the revocation
+        let user = &key_pair.public_key.details.users[0];
+        assert_eq!(user.signatures.len(), 2);
+        assert!(user.signatures.contains(&certification));
+        assert!(is_self_signature(&user.signatures[1], &key_pair.public_key));
+        let subkey = &key_pair.public_key.public_subkeys[0];
+        assert_eq!(subkey.signatures.len(), 2);
+        assert!(subkey.signatures.contains(&revocation));
+        assert_eq!(subkey.signatures[1].typ(), SignatureType::SubkeyBinding);
+        assert_eq!(subkey_expires_at(subkey), Some(expires_at));
+        assert!(revocation::subkey_revocation(&key_pair.public_key.primary_key, subkey).is_some());
+    }
+
+    #[test]
+    fn test_key_pair_layout() {
+        let key_pair = KeyPair::builder("foo bar baz")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .authentication_subkey(true)
+            .build()
+            .unwrap();
+
+        let primary_flags = key_pair.public_key.details.users[0].signatures[0].key_flags();
+        assert!(primary_flags.certify());
+        assert!(primary_flags.sign());
+        assert!(!primary_flags.encrypt_comms());
+
+        let subkeys = &key_pair.public_key.public_subkeys;
+        assert_eq!(subkeys.len(), 2);
+        let encryption_flags = subkeys[0].signatures[0].key_flags();
+        assert!(encryption_flags.encrypt_comms());
+        assert!(encryption_flags.encrypt_storage());
+        assert!(!encryption_flags.sign());
+        let authentication_flags = subkeys[1].signatures[0].key_flags();
+        assert!(authentication_flags.authentication());
+        assert!(!authentication_flags.encrypt_comms());
+    }
+
+    #[test]
+    fn test_key_pair_inspection() {
+        let key_pair = KeyPair::builder("Alice <alice@example.com>")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .authentication_subkey(true)
+            .expiration(Duration::days(365))
+            .build()
+            .unwrap();
+        assert_eq!(key_pair.fingerprint(), key_pair.public_key.fingerprint());
+        assert_eq!(key_pair.key_id(), key_pair.public_key.key_id());
+        assert_eq!(key_pair.user_ids(), vec!["Alice <alice@example.com>"]);
+        assert_eq!(key_pair.creation_time(), *key_pair.public_key.created_at());
+        assert_eq!(key_pair.algorithm(), PublicKeyAlgorithm::EdDSALegacy);
+        assert_eq!(key_pair.bit_length(), Some(255));
+        assert_eq!(key_pair.capabilities().to_string(), "SC");
+
+        let subkeys = key_pair.subkeys();
+        assert_eq!(subkeys.len(), 2);
+        assert_eq!(subkeys[0].algorithm, PublicKeyAlgorithm::ECDH);
+        assert_eq!(subkeys[0].bit_length, Some(255));
+        assert_eq!(subkeys[0].capabilities.to_string(), "E");
+        assert_eq!(
+            subkeys[0].key_id,
+            key_pair.public_key.public_subkeys[0].key.key_id()
+        );
+        assert_eq!(subkeys[1].capabilities.to_string(), "A");
+
+        // Newer revocations and third-party certifications carry no key flags and are ignored
+        let mut revoked = KeyPair::from_secret_key(key_pair.secret_key.clone());
+        let other = KeyPair::builder("Mallory").build().unwrap();
+        let user = &mut revoked.public_key.details.users[0];
+        let mut revocation = user.signatures[0].clone();
+        revocation.config.typ = SignatureType::CertRevocation;
+        let mut third_party = user.signatures[0].clone();
+        for signature in [&mut revocation, &mut third_party] {
+            signature.config.hashed_subpackets.retain(|subpacket| {
+                !matches!(
+                    subpacket.data,
+                    SubpacketData::SignatureCreationTime(_) | SubpacketData::KeyFlags(_)
+                )
+            });
+            signature.config.hashed_subpackets.push(Subpacket::regular(
+                SubpacketData::SignatureCreationTime(key_pair.creation_time() + Duration::hours(1)),
+            ));
+        }
+        third_party
+            .config
+            .hashed_subpackets
+            .retain(|subpacket| !matches!(subpacket.data, SubpacketData::IssuerFingerprint(_)));
+        third_party.config.unhashed_subpackets =
+            vec![Subpacket::regular(SubpacketData::Issuer(other.key_id()))];
+        user.signatures.extend([revocation, third_party]);
+        assert_eq!(revoked.capabilities().to_string(), "SC");
+
+        let created = key_pair.creation_time().format("%Y-%m-%d");
+        let expires = key_pair.expires_at().unwrap().format("%Y-%m-%d");
+        let fingerprint = fingerprint_hex(&key_pair.fingerprint());
+        assert_eq!(
+            key_pair.summary(),
+            format!(
+                "pub   ed25519 {created} [SC] [expires: {expires}]\n      {fingerprint}\n\
+                 uid           Alice <alice@example.com>\n\
+                 sub   cv25519 {created} [E] [expires: {expires}]\n\
+                 sub   ed25519 {created} [A] [expires: {expires}]\n"
+            )
+        );
+
+        let key_pair = KeyPair::builder("foo bar baz")
+            .algorithm(KeyAlgorithm::NistP384)
+            .build()
+            .unwrap();
+        assert_eq!(key_pair.bit_length(), Some(384));
+        assert!(key_pair.summary().starts_with("pub   nistp384 "));
+        assert!(key_pair.summary().contains("\nsub   nistp384 "));
     }
 }
@@ -0,0 +1,726 @@
+//! A keyring kept in a directory, with separate stores for public and secret keys
+//!
+//! Every key is saved as an armored file named after its fingerprint, in `public/` or
+//! `secret/` under the keyring directory. Keys can be looked up by fingerprint, long key ID or
+//! part of a user ID, and the keyring can encrypt to, decrypt with and verify against the keys
+//! it holds.
+use crate::cert::{CertError, PublicCert, PublicKeyProvider};
+use crate::decrypt::{decrypt_with_keys_and_passphrase, DecryptError, DecryptedMessage};
+use crate::encrypt::{encrypt_to_recipients, EncryptError};
+use crate::keypair::{
+    fingerprint_hex, is_self_signature, KeyPair, KeyPairError, PassphraseProvider,
+};
+use crate::signing::{
+    verify_file_signature_with_certs, verify_signed_data_with_certs,
+    verify_signed_message_with_certs, SigningError, VerificationReport,
+};
+use pgp::composed::{from_armor_many, from_bytes_many, PublicOrSecret};
+use pgp::packet::Signature;
+use pgp::types::{Fingerprint, PublicKeyTrait};
+use pgp::{Deserializable, SignedPublicKey, SignedSecretKey};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use thiserror::Error as ThisError;
+
+#[derive(Debug, ThisError)]
+pub enum KeyringError {
+    #[error("Failed to access keyring: {path} | Error: {source}")]
+    IoError {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to read key: {0}")]
+    ParseError(String),
+    #[error("No key in the keyring matches {0:?}")]
+    KeyNotFound(String),
+    #[error("More than one key in the keyring matches {0:?}")]
+    AmbiguousKey(String),
+    #[error("Certificate error: {0}")]
+    CertError(#[from] CertError),
+    #[error("Key pair error: {0}")]
+    KeyPairError(#[from] KeyPairError),
+    #[error("Encryption error: {0}")]
+    EncryptError(#[from] EncryptError),
+    #[error("Decryption error: {0}")]
+    DecryptError(#[from] DecryptError),
+    #[error("Signing error: {0}")]
+    SigningError(#[from] SigningError),
+}
+
+/// What a lookup string is matched against, see `Keyring::find_public_keys`
+enum KeyQuery {
+    Fingerprint(Vec<u8>),
+    KeyId(Vec<u8>),
+    UserId(String),
+}
+
+impl KeyQuery {
+    /// Reads a query as a fingerprint or long key ID if it is 40, 64 or 16 hex digits, and as
+    /// part of a user ID otherwise
+    fn parse(query: &str) -> Self {
+        let trimmed = query.trim();
+        let digits: String = trimmed
+            .strip_prefix("0x")
+            .or_else(|| trimmed.strip_prefix("0X"))
+            .unwrap_or(trimmed)
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        match decode_hex(&digits) {
+            Some(bytes) if bytes.len() == 20 || bytes.len() == 32 => KeyQuery::Fingerprint(bytes),
+            Some(bytes) if bytes.len() == 8 => KeyQuery::KeyId(bytes),
+            _ => KeyQuery::UserId(trimmed.to_lowercase()),
+        }
+    }
+
+    /// Returns true if the primary key or a subkey has the fingerprint or key ID, or a user
+    /// ID contains the text, ignoring case
+    fn matches(&self, key: &SignedPublicKey) -> bool {
+        let subkeys = key.public_subkeys.iter().map(|subkey| &subkey.key);
+        match self {
+            KeyQuery::Fingerprint(fingerprint) => {
+                key.fingerprint().as_bytes() == fingerprint.as_slice()
+                    || subkeys
+                        .into_iter()
+                        .any(|subkey| subkey.fingerprint().as_bytes() == fingerprint.as_slice())
+            }
+            KeyQuery::KeyId(key_id) => {
+                key.key_id().as_ref() == key_id.as_slice()
+                    || subkeys
+                        .into_iter()
+                        .any(|subkey| subkey.key_id().as_ref() == key_id.as_slice())
+            }
+            KeyQuery::UserId(text) => key.details.users.iter().any(|user| {
+                String::from_utf8_lossy(user.id.id())
+                    .to_lowercase()
+                    .contains(text.as_str())
+            }),
+        }
+    }
+}
+
+/// Decodes a string of hex digits, returning None if it is not one
+fn decode_hex(digits: &str) -> Option<Vec<u8>> {
+    if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// A directory of public and secret keys
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    directory: PathBuf,
+}
+
+impl Keyring {
+    /// Opens the keyring in a directory, creating the directory and its stores if needed
+    pub fn open<P: AsRef<Path>>(directory: P) -> Result<Self, KeyringError> {
+        let keyring = Keyring {
+            directory: directory.as_ref().to_path_buf(),
+        };
+        for store in [keyring.public_store(), keyring.secret_store()] {
+            std::fs::create_dir_all(&store).map_err(|e| KeyringError::IoError {
+                path: store.clone(),
+                source: e,
+            })?;
+        }
+        Ok(keyring)
+    }
+
+    /// Returns the directory the keyring is kept in
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn public_store(&self) -> PathBuf {
+        self.directory.join("public")
+    }
+
+    fn secret_store(&self) -> PathBuf {
+        self.directory.join("secret")
+    }
+
+    fn public_key_path(&self, fingerprint: &Fingerprint) -> PathBuf {
+        self.public_store()
+            .join(format!("{}.asc", fingerprint_hex(fingerprint)))
+    }
+
+    fn secret_key_path(&self, fingerprint: &Fingerprint) -> PathBuf {
+        self.secret_store()
+            .join(format!("{}.asc", fingerprint_hex(fingerprint)))
+    }
+
+    /// Adds a public key to the keyring, merging it with any copy already in it
+    ///
+    /// Signatures from both copies are kept, so importing an older copy of a key does not undo
+    /// a revocation or drop certifications.
+    ///
+    /// # Returns
+    /// The fingerprint of the key
+    pub fn import_public_key<K>(&self, public_key: &K) -> Result<Fingerprint, KeyringError>
+    where
+        K: PublicKeyProvider + ?Sized,
+    {
+        let imported = public_key.signed_public_key();
+        let fingerprint = imported.fingerprint();
+        let path = self.public_key_path(&fingerprint);
+        let public_key = if path.exists() {
+            let (stored, _) = SignedPublicKey::from_string(&read_file(&path)?)
+                .map_err(|e| KeyringError::ParseError(format!("{}: {}", path.display(), e)))?;
+            merge_public_keys(stored, imported)
+        } else {
+            imported.clone()
+        };
+        let cert = PublicCert::new(public_key)?;
+        write_file(&path, &cert.to_armored_string()?)?;
+        Ok(fingerprint)
+    }
+
+    /// Adds a key pair to the keyring, merging the public key with any copy already in it and
+    /// replacing any stored secret key
+    ///
+    /// A passphrase protected secret key is stored in its locked form.
+    ///
+    /// # Returns
+    /// The fingerprint of the key
+    pub fn import_key_pair(&self, key_pair: &KeyPair) -> Result<Fingerprint, KeyringError> {
+        let fingerprint = self.import_public_key(key_pair)?;
+        write_file(
+            &self.secret_key_path(&fingerprint),
+            &key_pair.secret_key_armored_string()?,
+        )?;
+        Ok(fingerprint)
+    }
+
+    /// Adds every key in an armored or binary key block, as written by `gpg --export` or
+    /// `gpg --export-secret-keys`
+    ///
+    /// The public key of each secret key is stored as well.
+    ///
+    /// # Returns
+    /// The fingerprints of the imported keys, in the order they appear in the block
+    pub fn import(&self, keys: &[u8]) -> Result<Vec<Fingerprint>, KeyringError> {
+        let start = keys
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(keys.len());
+        let parsed = if keys[start..].starts_with(b"-----BEGIN PGP") {
+            from_armor_many(Cursor::new(keys))
+                .map_err(|e| KeyringError::ParseError(e.to_string()))?
+                .0
+        } else {
+            from_bytes_many(Cursor::new(keys))
+        };
+
+        let mut fingerprints = Vec::new();
+        for key in parsed {
+            let fingerprint = match key.map_err(|e| KeyringError::ParseError(e.to_string()))? {
+                PublicOrSecret::Public(public_key) => self.import_public_key(&public_key)?,
+                PublicOrSecret::Secret(secret_key) => {
+                    self.import_key_pair(&KeyPair::from_secret_key(secret_key))?
+                }
+            };
+            fingerprints.push(fingerprint);
+        }
+        if fingerprints.is_empty() {
+            return Err(KeyringError::ParseError("no keys found".to_string()));
+        }
+        Ok(fingerprints)
+    }
+
+    /// Returns the armored public key with the fingerprint
+    pub fn export_public_key(&self, fingerprint: &Fingerprint) -> Result<String, KeyringError> {
+        read_key(&self.public_key_path(fingerprint), fingerprint)
+    }
+
+    /// Returns the armored secret key with the fingerprint, locked if it has a passphrase
+    pub fn export_secret_key(&self, fingerprint: &Fingerprint) -> Result<String, KeyringError> {
+        read_key(&self.secret_key_path(fingerprint), fingerprint)
+    }
+
+    /// Removes a key from the keyring, both its public key and its secret key if it has one
+    pub fn delete_key(&self, fingerprint: &Fingerprint) -> Result<(), KeyringError> {
+        let secret = remove_file(&self.secret_key_path(fingerprint))?;
+        let public = remove_file(&self.public_key_path(fingerprint))?;
+        if !secret && !public {
+            return Err(KeyringError::KeyNotFound(fingerprint_hex(fingerprint)));
+        }
+        Ok(())
+    }
+
+    /// Removes only the secret key, keeping the public key for encrypting and verifying
+    pub fn delete_secret_key(&self, fingerprint: &Fingerprint) -> Result<(), KeyringError> {
+        if !remove_file(&self.secret_key_path(fingerprint))? {
+            return Err(KeyringError::KeyNotFound(fingerprint_hex(fingerprint)));
+        }
+        Ok(())
+    }
+
+    /// Returns every public key in the keyring, ordered by fingerprint
+    pub fn public_keys(&self) -> Result<Vec<PublicCert>, KeyringError> {
+        key_files(&self.public_store())?
+            .iter()
+            .map(|path| {
+                let file = std::fs::File::open(path).map_err(|e| KeyringError::IoError {
+                    path: path.clone(),
+                    source: e,
+                })?;
+                let (public_key, _) = SignedPublicKey::from_armor_single(file)
+                    .map_err(|e| KeyringError::ParseError(format!("{}: {}", path.display(), e)))?;
+                Ok(PublicCert::new(public_key)?)
+            })
+            .collect()
+    }
+
+    /// Returns every key pair in the keyring, ordered by fingerprint
+    ///
+    /// Each secret key is paired with the public key stored for it, which may carry newer
+    /// signatures such as a revocation.
+    pub fn secret_keys(&self) -> Result<Vec<KeyPair>, KeyringError> {
+        key_files(&self.secret_store())?
+            .iter()
+            .map(|path| {
+                let secret_key = read_file(path)?;
+                let (parsed, _) = SignedSecretKey::from_string(&secret_key)
+                    .map_err(|e| KeyringError::ParseError(format!("{}: {}", path.display(), e)))?;
+                let public_path = self.public_key_path(&parsed.fingerprint());
+                let key_pair = if public_path.exists() {
+                    KeyPair::from_armored_strings(&secret_key, &read_file(&public_path)?)?
+                } else {
+                    KeyPair::from_secret_key_armored(&secret_key)?
+                };
+                Ok(key_pair)
+            })
+            .collect()
+    }
+
+    /// Returns the public keys matching a fingerprint, a long key ID or part of a user ID
+    ///
+    /// A query of 40 or 64 hex digits is a fingerprint and one of 16 hex digits is a long key
+    /// ID, with or without spaces and a `0x` prefix. Both match subkeys too. Anything else
+    /// matches keys with a user ID containing the text, ignoring case, such as an email address.
+    pub fn find_public_keys(&self, query: &str) -> Result<Vec<PublicCert>, KeyringError> {
+        let query = KeyQuery::parse(query);
+        let mut keys = self.public_keys()?;
+        keys.retain(|cert| query.matches(cert.public_key()));
+        Ok(keys)
+    }
+
+    /// Returns the key pairs matching a query, see `find_public_keys`
+    pub fn find_secret_keys(&self, query: &str) -> Result<Vec<KeyPair>, KeyringError> {
+        let query = KeyQuery::parse(query);
+        let mut keys = self.secret_keys()?;
+        keys.retain(|key_pair| query.matches(key_pair.public_key()));
+        Ok(keys)
+    }
+
+    /// Returns the one public key matching a query
+    ///
+    /// Fails with `KeyNotFound` if no key matches and `AmbiguousKey` if several do.
+    pub fn public_key(&self, query: &str) -> Result<PublicCert, KeyringError> {
+        single_match(query, self.find_public_keys(query)?)
+    }
+
+    /// Returns the one key pair matching a query, see `public_key`
+    pub fn secret_key(&self, query: &str) -> Result<KeyPair, KeyringError> {
+        single_match(query, self.find_secret_keys(query)?)
+    }
+
+    /// Encrypts a message to the recipients, each given as a query matching exactly one key
+    pub fn encrypt(&self, msg: &str, recipients: &[&str]) -> Result<String, KeyringError> {
+        let certs = recipients
+            .iter()
+            .map(|recipient| self.public_key(recipient))
+            .collect::<Result<Vec<_>, _>>()?;
+        let recipients: Vec<&SignedPublicKey> = certs.iter().map(PublicCert::public_key).collect();
+        Ok(encrypt_to_recipients(msg, &recipients)?)
+    }
+
+    /// Decrypts a binary or armored message with whichever secret key in the keyring it was
+    /// encrypted to, see `decrypt::decrypt_with_keys_and_passphrase`
+    pub fn decrypt(&self, encrypted: &[u8]) -> Result<DecryptedMessage, KeyringError> {
+        self.decrypt_with_passphrase(encrypted, "")
+    }
+
+    /// Decrypts a message with a passphrase protected secret key from the keyring
+    pub fn decrypt_with_passphrase<P>(
+        &self,
+        encrypted: &[u8],
+        passphrase: &P,
+    ) -> Result<DecryptedMessage, KeyringError>
+    where
+        P: PassphraseProvider + ?Sized,
+    {
+        let key_pairs = self.secret_keys()?;
+        let secret_keys: Vec<&SignedSecretKey> =
+            key_pairs.iter().map(KeyPair::secret_key).collect();
+        Ok(decrypt_with_keys_and_passphrase(
+            encrypted,
+            &secret_keys,
+            passphrase,
+        )?)
+    }
+
+    /// Verifies a signed message against the public keys in the keyring
+    pub fn verify_signed_message(
+        &self,
+        signed_message_armored: &str,
+    ) -> Result<(String, VerificationReport), KeyringError> {
+        let certs = self.public_keys()?;
+        Ok(verify_signed_message_with_certs(
+            &public_keys(&certs),
+            signed_message_armored,
+        )?)
+    }
+
+    /// Verifies a detached signature over data against the public keys in the keyring
+    pub fn verify_signed_data(
+        &self,
+        data: &[u8],
+        signature_armored: &str,
+    ) -> Result<VerificationReport, KeyringError> {
+        let certs = self.public_keys()?;
+        Ok(verify_signed_data_with_certs(
+            &public_keys(&certs),
+            data,
+            signature_armored,
+        )?)
+    }
+
+    /// Verifies a detached signature over a file against the public keys in the keyring
+    pub fn verify_file_signature<P: AsRef<Path>>(
+        &self,
+        file_path: P,
+        signature_armored: &str,
+    ) -> Result<VerificationReport, KeyringError> {
+        let certs = self.public_keys()?;
+        Ok(verify_file_signature_with_certs(
+            &public_keys(&certs),
+            file_path,
+            signature_armored,
+        )?)
+    }
+}
+
+/// Adds the signatures from `imported` that are not in `signatures` yet
+fn merge_signatures(signatures: &mut Vec<Signature>, imported: &[Signature]) {
+    for signature in imported {
+        if !signatures.contains(signature) {
+            signatures.push(signature.clone());
+        }
+    }
+}
+
+/// Drops self-signatures that a newer self-signature in the same set replaces
+///
+/// The PGP library reads the expiration of a key from all of its self-signatures, so an older
+/// self-signature must not survive a merge and bring back an expiration date changed since.
+fn drop_superseded(signatures: &mut Vec<Signature>, primary: &impl PublicKeyTrait) {
+    let newest = signatures
+        .iter()
+        .filter(|sig| is_self_signature(sig, primary))
+        .map(|sig| sig.created().copied())
+        .max();
+    signatures
+        .retain(|sig| !is_self_signature(sig, primary) || Some(sig.created().copied()) == newest);
+}
+
+/// Combines a stored copy of a key with an imported copy of the same key
+///
+/// User IDs and subkeys are matched up and their signatures united, so revocations and
+/// certifications from either copy are kept, along with the newest self-signatures.
+fn merge_public_keys(mut stored: SignedPublicKey, imported: &SignedPublicKey) -> SignedPublicKey {
+    let primary = stored.primary_key.clone();
+    let details = &mut stored.details;
+    merge_signatures(
+        &mut details.revocation_signatures,
+        &imported.details.revocation_signatures,
+    );
+    merge_signatures(
+        &mut details.direct_signatures,
+        &imported.details.direct_signatures,
+    );
+    drop_superseded(&mut details.direct_signatures, &primary);
+
+    for user in &imported.details.users {
+        match details.users.iter_mut().find(|stored| stored.id == user.id) {
+            Some(stored) => merge_signatures(&mut stored.signatures, &user.signatures),
+            None => details.users.push(user.clone()),
+        }
+    }
+    for user in &mut details.users {
+        drop_superseded(&mut user.signatures, &primary);
+    }
+    for attribute in &imported.details.user_attributes {
+        match details
+            .user_attributes
+            .iter_mut()
+            .find(|stored| stored.attr == attribute.attr)
+        {
+            Some(stored) => merge_signatures(&mut stored.signatures, &attribute.signatures),
+            None => details.user_attributes.push(attribute.clone()),
+        }
+    }
+    for attribute in &mut details.user_attributes {
+        drop_superseded(&mut attribute.signatures, &primary);
+    }
+
+    for subkey in &imported.public_subkeys {
+        match stored
+            .public_subkeys
+            .iter_mut()
+            .find(|stored| stored.key.fingerprint() == subkey.key.fingerprint())
+        {
+            Some(stored) => merge_signatures(&mut stored.signatures, &subkey.signatures),
+            None => stored.public_subkeys.push(subkey.clone()),
+        }
+    }
+    for subkey in &mut stored.public_subkeys {
+        drop_superseded(&mut subkey.signatures, &primary);
+    }
+    stored
+}
+
+fn public_keys(certs: &[PublicCert]) -> Vec<&SignedPublicKey> {
+    certs.iter().map(PublicCert::public_key).collect()
+}
+
+fn single_match<T>(query: &str, mut keys: Vec<T>) -> Result<T, KeyringError> {
+    match keys.len() {
+        0 => Err(KeyringError::KeyNotFound(query.to_string())),
+        1 => Ok(keys.remove(0)),
+        _ => Err(KeyringError::AmbiguousKey(query.to_string())),
+    }
+}
+
+/// Lists the key files in a store, sorted by name
+fn key_files(store: &Path) -> Result<Vec<PathBuf>, KeyringError> {
+    let io_error = |e| KeyringError::IoError {
+        path: store.to_path_buf(),
+        source: e,
+    };
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(store).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.extension().is_some_and(|extension| extension == "asc") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn read_file(path: &Path) -> Result<String, KeyringError> {
+    std::fs::read_to_string(path).map_err(|e| KeyringError::IoError {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+fn read_key(path: &Path, fingerprint: &Fingerprint) -> Result<String, KeyringError> {
+    if !path.exists() {
+        return Err(KeyringError::KeyNotFound(fingerprint_hex(fingerprint)));
+    }
+    read_file(path)
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), KeyringError> {
+    std::fs::write(path, contents).map_err(|e| KeyringError::IoError {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+/// Removes a file, returning false if it did not exist
+fn remove_file(path: &Path) -> Result<bool, KeyringError> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(KeyringError::IoError {
+            path: path.to_path_buf(),
+            source: e,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::KeyAlgorithm;
+    use crate::signing::{sign_data_from_reader, sign_message};
+    use crate::test_util::TestDir;
+    use pgp::ser::Serialize;
+
+    #[test]
+    fn test_keyring_import_merges() {
+        use crate::revocation::{
+            apply_revocation_certificate, revocation_status, RevocationReason,
+        };
+        use chrono::{Duration, SubsecRound, Utc};
+
+        let directory = TestDir::new("test_pgp_keyring_merge");
+        let keyring = Keyring::open(directory.join("keyring")).unwrap();
+        let mut key_pair = KeyPair::builder("Dave <dave@example.com>")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .created_at(Utc::now() - Duration::days(1))
+            .expiration(Duration::days(365))
+            .build()
+            .unwrap();
+        let original = key_pair.public_key().clone();
+        let fingerprint = keyring.import_public_key(&original).unwrap();
+
+        // A shortened expiration is kept when the older copy is imported again
+        let expires_at = (Utc::now() + Duration::days(30)).trunc_subsecs(0);
+        key_pair.set_expiration(Some(expires_at)).unwrap();
+        keyring.import_public_key(&key_pair).unwrap();
+        keyring.import_public_key(&original).unwrap();
+        let stored = keyring.public_key(&fingerprint_hex(&fingerprint)).unwrap();
+        assert_eq!(stored.public_key().expires_at(), Some(expires_at));
+
+        // A revoked key stays revoked when an unrevoked copy is imported
+        let certificate = key_pair
+            .generate_revocation_certificate(RevocationReason::KeyRetired, "")
+            .unwrap();
+        let revoked = apply_revocation_certificate(key_pair.public_key(), &certificate).unwrap();
+        keyring.import_public_key(&revoked).unwrap();
+        keyring.import_key_pair(&key_pair).unwrap();
+        keyring
+            .import(
+                original
+                    .to_armored_string(Default::default())
+                    .unwrap()
+                    .as_bytes(),
+            )
+            .unwrap();
+        let stored = keyring.public_key(&fingerprint_hex(&fingerprint)).unwrap();
+        assert_eq!(
+            revocation_status(stored.public_key()).unwrap().reason,
+            RevocationReason::KeyRetired
+        );
+        assert_eq!(
+            keyring.secret_keys().unwrap()[0].public_key(),
+            stored.public_key()
+        );
+        assert_eq!(keyring.public_keys().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_keyring() {
+        let directory = TestDir::new("test_pgp_keyring");
+        let keyring = Keyring::open(directory.join("keyring")).unwrap();
+        let alice = KeyPair::builder("Alice <alice@example.com>")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        let bob = KeyPair::builder("Bob <bob@example.org>")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .passphrase("correct horse")
+            .build()
+            .unwrap();
+        let carol = KeyPair::builder("Carol <carol@example.org>")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+
+        let alice_fp = keyring.import_key_pair(&alice).unwrap();
+        let bob_fp = keyring
+            .import(bob.secret_key_armored_string().unwrap().as_bytes())
+            .unwrap()[0]
+            .clone();
+        let carol_fp = keyring
+            .import(&carol.public_key().to_bytes().unwrap())
+            .unwrap()[0]
+            .clone();
+        assert_eq!(keyring.public_keys().unwrap().len(), 3);
+        assert_eq!(keyring.secret_keys().unwrap().len(), 2);
+        assert!(keyring
+            .secret_key(&fingerprint_hex(&bob_fp))
+            .unwrap()
+            .is_passphrase_protected());
+
+        // Lookup by fingerprint, long key ID of a subkey, and user ID
+        let cert = keyring.public_key(&fingerprint_hex(&alice_fp)).unwrap();
+        assert_eq!(cert.public_key(), alice.public_key());
+        let subkey_id = format!("0x{:X}", carol.public_key().public_subkeys[0].key.key_id());
+        assert_eq!(
+            keyring.public_key(&subkey_id).unwrap().public_key(),
+            carol.public_key()
+        );
+        assert_eq!(
+            keyring.public_key("BOB@example").unwrap().public_key(),
+            bob.public_key()
+        );
+        assert_eq!(keyring.find_public_keys("example.org").unwrap().len(), 2);
+        assert!(matches!(
+            keyring.public_key("example.org"),
+            Err(KeyringError::AmbiguousKey(_))
+        ));
+        assert!(matches!(
+            keyring.secret_key("carol"),
+            Err(KeyringError::KeyNotFound(_))
+        ));
+
+        // Encrypt, decrypt and verify with the stored keys
+        let encrypted = keyring.encrypt("Hello, keyring!", &["alice@"]).unwrap();
+        let decrypted = keyring.decrypt(encrypted.as_bytes()).unwrap();
+        assert_eq!(decrypted.data, b"Hello, keyring!");
+        assert_eq!(decrypted.fingerprint, alice_fp);
+        let encrypted = keyring.encrypt("Hello, Bob!", &["bob@"]).unwrap();
+        assert_eq!(
+            keyring
+                .decrypt_with_passphrase(encrypted.as_bytes(), "correct horse")
+                .unwrap()
+                .data,
+            b"Hello, Bob!"
+        );
+        assert!(matches!(
+            keyring.decrypt_with_passphrase(encrypted.as_bytes(), "battery staple"),
+            Err(KeyringError::DecryptError(DecryptError::WrongPassphrase))
+        ));
+        let encrypted = keyring.encrypt("Hello, Carol!", &["carol@"]).unwrap();
+        assert!(matches!(
+            keyring.decrypt(encrypted.as_bytes()),
+            Err(KeyringError::DecryptError(DecryptError::NoMatchingKey(_)))
+        ));
+        let signed = sign_message(&carol, "Signed by Carol").unwrap();
+        let (message, report) = keyring.verify_signed_message(&signed).unwrap();
+        assert_eq!(message, "Signed by Carol");
+        assert!(report.is_valid());
+        let signature = sign_data_from_reader(&alice, &mut &b"Hello, keyring!"[..]).unwrap();
+        assert!(keyring
+            .verify_signed_data(b"Hello, keyring!", &signature)
+            .unwrap()
+            .is_valid());
+
+        // Export and delete
+        assert_eq!(
+            keyring.export_public_key(&alice_fp).unwrap(),
+            alice.public_key_armored_string().unwrap()
+        );
+        assert_eq!(
+            keyring.export_secret_key(&alice_fp).unwrap(),
+            alice.secret_key_armored_string().unwrap()
+        );
+        keyring.delete_secret_key(&alice_fp).unwrap();
+        assert!(matches!(
+            keyring.export_secret_key(&alice_fp),
+            Err(KeyringError::KeyNotFound(_))
+        ));
+        keyring.public_key("alice@").unwrap();
+        keyring.delete_key(&carol_fp).unwrap();
+        assert!(matches!(
+            keyring.delete_key(&carol_fp),
+            Err(KeyringError::KeyNotFound(_))
+        ));
+        assert_eq!(keyring.public_keys().unwrap().len(), 2);
+        assert_eq!(
+            keyring.verify_signed_message(&signed).unwrap().1.failure,
+            Some(crate::signing::VerificationFailure::UnknownKey)
+        );
+    }
+}
@@ -1,5 +1,12 @@
+pub mod cert;
 pub mod decrypt;
 pub mod encrypt;
 pub mod keypair;
+pub mod keyring;
+pub mod revocation;
 pub mod signing;
+pub mod stream;
 pub mod validate;
+
+#[cfg(test)]
+mod test_util;
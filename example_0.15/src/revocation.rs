@@ -0,0 +1,341 @@
+//! Create and apply key revocation certificates
+//!
+//! A revocation certificate is a key revocation signature made by the primary key. Generate one
+//! right after creating a key and keep it somewhere safe, so the key can still be revoked if the
+//! secret key is lost or compromised.
+//!
+//! Certificates are armored as a public key block, like the ones from `gpg --gen-revoke`, so they
+//! can be imported with `gpg --import`.
+use chrono::{SubsecRound, Utc};
+use pgp::{
+    armor::{BlockType, Dearmor},
+    composed::{signed_key::*, StandaloneSignature},
+    packet::{RevocationCode, Signature, SignatureConfig, SignatureType, Subpacket, SubpacketData},
+    types::{KeyId, PublicKeyTrait, SecretKeyTrait},
+    Deserializable,
+};
+use std::io::{Cursor, Read};
+use thiserror::Error as ThisError;
+
+#[derive(Debug, ThisError)]
+pub enum RevocationError {
+    #[error("Invalid revocation certificate: {0}")]
+    InvalidCertificate(String),
+    #[error("Revocation certificate was not issued by key {0:?}")]
+    WrongKey(KeyId),
+    #[error("PGP error: {0}")]
+    PgpError(#[from] pgp::errors::Error),
+}
+
+/// Why a key was revoked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RevocationReason {
+    /// No reason given
+    #[default]
+    NoReason,
+    /// The key was replaced by a new one
+    KeySuperseded,
+    /// The secret key material may have been exposed
+    KeyCompromised,
+    /// The key is no longer used
+    KeyRetired,
+    /// The user ID is no longer valid. Only meaningful for user ID revocations, so it can be
+    /// read from a key but not used for a revocation certificate.
+    UserIdInvalid,
+    /// A code without a defined meaning, such as one from the private use range
+    Other(u8),
+}
+
+impl From<RevocationReason> for RevocationCode {
+    fn from(reason: RevocationReason) -> Self {
+        match reason {
+            RevocationReason::NoReason => RevocationCode::NoReason,
+            RevocationReason::KeySuperseded => RevocationCode::KeySuperseded,
+            RevocationReason::KeyCompromised => RevocationCode::KeyCompromised,
+            RevocationReason::KeyRetired => RevocationCode::KeyRetired,
+            RevocationReason::UserIdInvalid => RevocationCode::CertUserIdInvalid,
+            RevocationReason::Other(code) => RevocationCode::from(code),
+        }
+    }
+}
+
+impl From<&RevocationCode> for RevocationReason {
+    fn from(code: &RevocationCode) -> Self {
+        match code {
+            RevocationCode::KeySuperseded => RevocationReason::KeySuperseded,
+            RevocationCode::KeyCompromised => RevocationReason::KeyCompromised,
+            RevocationCode::KeyRetired => RevocationReason::KeyRetired,
+            RevocationCode::NoReason => RevocationReason::NoReason,
+            RevocationCode::CertUserIdInvalid => RevocationReason::UserIdInvalid,
+            code => RevocationReason::Other(u8::from(*code)),
+        }
+    }
+}
+
+impl std::fmt::Display for RevocationReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            RevocationReason::NoReason => "no reason specified",
+            RevocationReason::KeySuperseded => "key is superseded",
+            RevocationReason::KeyCompromised => "key has been compromised",
+            RevocationReason::KeyRetired => "key is no longer used",
+            RevocationReason::UserIdInvalid => "user ID is no longer valid",
+            RevocationReason::Other(code) => return write!(f, "reason code {}", code),
+        };
+        f.write_str(reason)
+    }
+}
+
+/// The reason and description taken from a key revocation signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revocation {
+    pub reason: RevocationReason,
+    pub description: String,
+}
+
+/// Creates an armored revocation certificate for the primary key of a secret key
+///
+/// # Returns
+/// `RevocationError::InvalidCertificate` for `RevocationReason::UserIdInvalid`, which does not
+/// apply to a key.
+pub fn generate_revocation_certificate(
+    secret_key: &SignedSecretKey,
+    passphrase: &str,
+    reason: RevocationReason,
+    description: &str,
+) -> Result<String, RevocationError> {
+    if reason == RevocationReason::UserIdInvalid {
+        return Err(RevocationError::InvalidCertificate(
+            "a key can not be revoked because a user ID is invalid".to_string(),
+        ));
+    }
+    let key = &secret_key.primary_key;
+    let mut config = SignatureConfig::v4(
+        SignatureType::KeyRevocation,
+        key.algorithm(),
+        key.hash_alg(),
+    );
+    config.hashed_subpackets = vec![
+        Subpacket::regular(SubpacketData::SignatureCreationTime(
+            Utc::now().trunc_subsecs(0),
+        )),
+        Subpacket::regular(SubpacketData::RevocationReason(
+            reason.into(),
+            description.into(),
+        )),
+        Subpacket::regular(SubpacketData::IssuerFingerprint(key.fingerprint())),
+    ];
+    config.unhashed_subpackets = vec![Subpacket::regular(SubpacketData::Issuer(key.key_id()))];
+
+    let signature = config.sign_key(key, || passphrase.to_string(), key)?;
+
+    let mut armored = Vec::new();
+    pgp::armor::write(
+        &StandaloneSignature::new(signature),
+        BlockType::PublicKey,
+        &mut armored,
+        None,
+        true,
+    )?;
+    String::from_utf8(armored).map_err(|e| RevocationError::InvalidCertificate(e.to_string()))
+}
+
+/// Reads the signature from an armored revocation certificate, either as a public key block
+/// or as a signature block
+fn parse_revocation_certificate(certificate: &str) -> Result<Signature, RevocationError> {
+    let mut bytes = Vec::new();
+    Dearmor::new(Cursor::new(certificate))
+        .read_to_end(&mut bytes)
+        .map_err(|e| RevocationError::InvalidCertificate(e.to_string()))?;
+    let signature = StandaloneSignature::from_bytes(&bytes[..])
+        .map_err(|e| RevocationError::InvalidCertificate(e.to_string()))?;
+    Ok(signature.signature)
+}
+
+/// Adds a revocation certificate to a public key after checking that it was issued by that key
+pub fn apply_revocation_certificate(
+    public_key: &SignedPublicKey,
+    certificate: &str,
+) -> Result<SignedPublicKey, RevocationError> {
+    let signature = parse_revocation_certificate(certificate)?;
+    if signature.typ() != SignatureType::KeyRevocation {
+        return Err(RevocationError::InvalidCertificate(format!(
+            "expected a key revocation signature, got {:?}",
+            signature.typ()
+        )));
+    }
+    signature
+        .verify_key(&public_key.primary_key)
+        .map_err(|_| RevocationError::WrongKey(public_key.key_id()))?;
+
+    let mut revoked = public_key.clone();
+    if !revoked.details.revocation_signatures.contains(&signature) {
+        revoked.details.revocation_signatures.push(signature);
+    }
+    Ok(revoked)
+}
+
+/// Returns the revocation of a key if it carries a valid key revocation signature
+pub fn revocation_status(public_key: &SignedPublicKey) -> Option<Revocation> {
+    key_revocation(
+        &public_key.details.revocation_signatures,
+        &public_key.primary_key,
+    )
+}
+
+/// Finds the first revocation signature that was made by the key itself
+pub(crate) fn key_revocation(
+    revocation_signatures: &[Signature],
+    key: &impl PublicKeyTrait,
+) -> Option<Revocation> {
+    key_revocations(revocation_signatures, key)
+        .next()
+        .map(Revocation::from)
+}
+
+/// Returns the key revocation signatures that were made by the key itself
+pub(crate) fn key_revocations<'a, K: PublicKeyTrait>(
+    revocation_signatures: &'a [Signature],
+    key: &'a K,
+) -> impl Iterator<Item = &'a Signature> + 'a {
+    revocation_signatures
+        .iter()
+        .filter(|sig| sig.typ() == SignatureType::KeyRevocation)
+        .filter(move |sig| sig.verify_key(key).is_ok())
+}
+
+/// Finds the first subkey revocation signature on a subkey that was made by its primary key
+pub(crate) fn subkey_revocation<'a>(
+    primary: &impl PublicKeyTrait,
+    subkey: &'a SignedPublicSubKey,
+) -> Option<&'a Signature> {
+    subkey
+        .signatures
+        .iter()
+        .filter(|sig| sig.typ() == SignatureType::SubkeyRevocation)
+        .find(|sig| sig.verify_key_binding(primary, &subkey.key).is_ok())
+}
+
+/// Returns the subkey revocation signatures on a subkey that were made by its primary key
+pub(crate) fn subkey_revocations<'a, K: PublicKeyTrait>(
+    primary: &'a K,
+    subkey: &'a SignedPublicSubKey,
+) -> impl Iterator<Item = &'a Signature> + 'a {
+    subkey
+        .signatures
+        .iter()
+        .filter(|sig| sig.typ() == SignatureType::SubkeyRevocation)
+        .filter(move |sig| sig.verify_key_binding(primary, &subkey.key).is_ok())
+}
+
+/// Returns true if a revocation invalidates a signature made by the revoked key
+///
+/// A key that was compromised, or revoked for no or an unknown reason, may have been used by
+/// someone else at any time, so all of its signatures are invalid. A superseded or retired key
+/// was fine until it was revoked, so only signatures made from then on are invalid.
+pub(crate) fn revokes_signature(revocation: &Signature, signature: &Signature) -> bool {
+    match revocation.revocation_reason_code() {
+        Some(RevocationCode::KeySuperseded | RevocationCode::KeyRetired) => {
+            match (revocation.created(), signature.created()) {
+                (Some(revoked_at), Some(created)) => created >= revoked_at,
+                _ => true,
+            }
+        }
+        _ => true,
+    }
+}
+
+impl From<&Signature> for Revocation {
+    fn from(signature: &Signature) -> Self {
+        Revocation {
+            reason: signature
+                .revocation_reason_code()
+                .map(RevocationReason::from)
+                .unwrap_or_default(),
+            description: signature
+                .revocation_reason_string()
+                .map(|reason| String::from_utf8_lossy(reason).into_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::{KeyAlgorithm, KeyPair, KeyPairError};
+
+    #[test]
+    fn test_revocation_certificate() {
+        let key_pair = KeyPair::builder("test@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        let certificate = key_pair
+            .generate_revocation_certificate(RevocationReason::KeyCompromised, "Laptop stolen")
+            .unwrap();
+        assert!(certificate.contains("BEGIN PGP PUBLIC KEY BLOCK"));
+        assert_eq!(revocation_status(key_pair.public_key()), None);
+
+        let revoked = apply_revocation_certificate(key_pair.public_key(), &certificate).unwrap();
+        revoked.verify().unwrap();
+        assert_eq!(
+            revocation_status(&revoked),
+            Some(Revocation {
+                reason: RevocationReason::KeyCompromised,
+                description: "Laptop stolen".to_string(),
+            })
+        );
+
+        // Applying the same certificate twice does not add a second signature
+        let revoked = apply_revocation_certificate(&revoked, &certificate).unwrap();
+        assert_eq!(revoked.details.revocation_signatures.len(), 1);
+
+        // A certificate for one key cannot revoke another
+        let other = KeyPair::builder("other@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            apply_revocation_certificate(other.public_key(), &certificate),
+            Err(RevocationError::WrongKey(_))
+        ));
+        assert!(matches!(
+            apply_revocation_certificate(other.public_key(), "not a certificate"),
+            Err(RevocationError::InvalidCertificate(_))
+        ));
+
+        // Codes without a named reason are kept as they are
+        let certificate = other
+            .generate_revocation_certificate(RevocationReason::Other(105), "")
+            .unwrap();
+        let revoked = apply_revocation_certificate(other.public_key(), &certificate).unwrap();
+        assert_eq!(
+            revocation_status(&revoked).unwrap().reason,
+            RevocationReason::Other(105)
+        );
+
+        // Code 32 is only defined for user ID revocations
+        assert!(matches!(
+            other.generate_revocation_certificate(RevocationReason::UserIdInvalid, ""),
+            Err(KeyPairError::RevocationError(
+                RevocationError::InvalidCertificate(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_revocation_certificate_at_creation() {
+        let (key_pair, certificate) = KeyPair::builder("test@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .passphrase("correct horse")
+            .build_with_revocation_certificate()
+            .unwrap();
+        assert_eq!(revocation_status(key_pair.public_key()), None);
+        let revoked = apply_revocation_certificate(key_pair.public_key(), &certificate).unwrap();
+        assert_eq!(
+            revocation_status(&revoked).unwrap().reason,
+            RevocationReason::NoReason
+        );
+    }
+}
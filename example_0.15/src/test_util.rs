@@ -0,0 +1,35 @@
+use std::path::{Path, PathBuf};
+
+/// A directory for test files, unique to the test and the process running it
+///
+/// The directory and everything in it is removed when the value is dropped, which also
+/// happens when an assertion fails and the test unwinds.
+pub(crate) struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Create a new empty directory
+    ///
+    /// # Arguments
+    /// * `name` - Identifies the test, so concurrent tests don't share a directory
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TestDir(path)
+    }
+
+    /// The path of a file or directory inside the test directory
+    pub(crate) fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
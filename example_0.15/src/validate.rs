@@ -1,13 +1,41 @@
-use pgp::{composed::signed_key::*, errors::Error, Deserializable};
+use crate::revocation::{key_revocation, RevocationReason};
+use pgp::{composed::signed_key::*, Deserializable};
+use thiserror::Error as ThisError;
 
-pub fn validate_public_key(key: &str) -> Result<(), Error> {
-    let (_, _) = SignedPublicKey::from_string(key)?;
-    Ok(())
+#[derive(Debug, ThisError)]
+pub enum ValidateError {
+    #[error("Key has been revoked ({reason}): {description}")]
+    Revoked {
+        reason: RevocationReason,
+        description: String,
+    },
+    #[error("PGP error: {0}")]
+    PgpError(#[from] pgp::errors::Error),
 }
 
-pub fn validate_secret_key(key: &str) -> Result<(), Error> {
-    let (_, _) = SignedSecretKey::from_string(key)?;
-    Ok(())
+/// Checks that the string holds a public key and that the key has not been revoked
+pub fn validate_public_key(key: &str) -> Result<(), ValidateError> {
+    let (key, _) = SignedPublicKey::from_string(key)?;
+    check_not_revoked(&key.details, &key.primary_key)
+}
+
+/// Checks that the string holds a secret key and that the key has not been revoked
+pub fn validate_secret_key(key: &str) -> Result<(), ValidateError> {
+    let (key, _) = SignedSecretKey::from_string(key)?;
+    check_not_revoked(&key.details, &key.primary_key)
+}
+
+fn check_not_revoked(
+    details: &SignedKeyDetails,
+    key: &impl pgp::types::PublicKeyTrait,
+) -> Result<(), ValidateError> {
+    match key_revocation(&details.revocation_signatures, key) {
+        Some(revocation) => Err(ValidateError::Revoked {
+            reason: revocation.reason,
+            description: revocation.description,
+        }),
+        None => Ok(()),
+    }
 }
 
 #[cfg(test)]
@@ -125,4 +153,33 @@ V2VfJKcDZiVqc9A/wOZKCLaeOJUjmeVTVPGkZrJ4hMo=
             "Expected empty secret key to fail validation"
         );
     }
+
+    #[test]
+    fn test_validate_public_key_revoked() {
+        use crate::keypair::{KeyAlgorithm, KeyPair};
+        use crate::revocation::apply_revocation_certificate;
+
+        let key_pair = KeyPair::builder("test@example.com")
+            .algorithm(KeyAlgorithm::Curve25519)
+            .build()
+            .unwrap();
+        let certificate = key_pair
+            .generate_revocation_certificate(RevocationReason::KeySuperseded, "Replaced by new key")
+            .unwrap();
+        let revoked = apply_revocation_certificate(key_pair.public_key(), &certificate).unwrap();
+        let armored = revoked
+            .to_armored_string(pgp::ArmorOptions::default())
+            .unwrap();
+
+        match validate_public_key(&armored) {
+            Err(ValidateError::Revoked {
+                reason,
+                description,
+            }) => {
+                assert_eq!(reason, RevocationReason::KeySuperseded);
+                assert_eq!(description, "Replaced by new key");
+            }
+            other => panic!("Expected revoked key error, got {:?}", other),
+        }
+    }
 }